@@ -4,7 +4,33 @@ use serde::{Deserialize, Serialize};
 pub struct Config {
     pub host: String,
     pub port: u16,
-    pub jwt_secret: String,
+    pub http_enabled: bool,
+    pub tls_port: u16,
+    pub tls_cert_file: Option<String>,
+    pub tls_key_file: Option<String>,
+    pub tls_client_ca_file: Option<String>,
+    pub cors_allowed_origins: Vec<String>,
+    pub environment: String,
+    pub jwt_secret: Option<String>,
+    pub jwks_file: Option<String>,
+    pub jwt_issuer: Option<String>,
+    pub jwt_audience: Option<String>,
+    pub insecure_dev_auth: bool,
+    pub solana_rpc_url: String,
+    pub payroll_program_id: String,
+    pub squads_program_id: String,
+    pub key_store_dir: String,
+    pub master_key: Option<String>,
+    pub master_key_file: String,
+    pub share_retention_hours: u64,
+    pub share_sweep_interval_secs: u64,
+    pub min_threshold: usize,
+    pub max_total_shares: usize,
+    pub keygen_idempotency_window_secs: u64,
+    pub sign_idempotency_window_secs: u64,
+    pub signing_session_ttl_secs: u64,
+    pub policy_file: Option<String>,
+    pub audit_log_file: String,
 }
 
 impl Config {
@@ -15,7 +41,75 @@ impl Config {
                 .unwrap_or_else(|_| "8080".to_string())
                 .parse()
                 .unwrap_or(8080),
-            jwt_secret: std::env::var("MPC_JWT_SECRET").unwrap_or_else(|_| "secret".to_string()),
+            http_enabled: std::env::var("MPC_HTTP_ENABLED")
+                .map(|value| value != "false" && value != "0")
+                .unwrap_or(true),
+            tls_port: std::env::var("MPC_TLS_PORT")
+                .unwrap_or_else(|_| "8443".to_string())
+                .parse()
+                .unwrap_or(8443),
+            tls_cert_file: std::env::var("MPC_TLS_CERT_FILE").ok().filter(|path| !path.is_empty()),
+            tls_key_file: std::env::var("MPC_TLS_KEY_FILE").ok().filter(|path| !path.is_empty()),
+            tls_client_ca_file: std::env::var("MPC_TLS_CLIENT_CA_FILE").ok().filter(|path| !path.is_empty()),
+            cors_allowed_origins: std::env::var("MPC_CORS_ALLOWED_ORIGINS")
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|origin| !origin.is_empty())
+                .map(str::to_string)
+                .collect(),
+            environment: std::env::var("MPC_ENV").unwrap_or_else(|_| "development".to_string()),
+            jwt_secret: std::env::var("MPC_JWT_SECRET").ok().filter(|secret| !secret.is_empty()),
+            jwks_file: std::env::var("MPC_JWKS_FILE").ok().filter(|path| !path.is_empty()),
+            jwt_issuer: std::env::var("MPC_JWT_ISSUER").ok().filter(|issuer| !issuer.is_empty()),
+            jwt_audience: std::env::var("MPC_JWT_AUDIENCE").ok().filter(|audience| !audience.is_empty()),
+            insecure_dev_auth: std::env::var("MPC_INSECURE_DEV_AUTH")
+                .map(|value| value == "true" || value == "1")
+                .unwrap_or(false),
+            solana_rpc_url: std::env::var("SOLANA_RPC_URL")
+                .unwrap_or_else(|_| "https://api.devnet.solana.com".to_string()),
+            payroll_program_id: std::env::var("PAYROLL_PROGRAM_ID")
+                .unwrap_or_else(|_| "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS".to_string()),
+            squads_program_id: std::env::var("SQUADS_PROGRAM_ID")
+                .unwrap_or_else(|_| "SQDS4ep65T869zMMBKyuUq6aD6EgTu8psMjkvj52pCf".to_string()),
+            key_store_dir: std::env::var("MPC_KEY_STORE_DIR").unwrap_or_else(|_| "data/keys".to_string()),
+            master_key: std::env::var("MPC_MASTER_KEY").ok().filter(|key| !key.is_empty()),
+            master_key_file: std::env::var("MPC_MASTER_KEY_FILE")
+                .unwrap_or_else(|_| "data/master.key".to_string()),
+            share_retention_hours: std::env::var("SHARE_RETENTION_HOURS")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse()
+                .unwrap_or(0),
+            share_sweep_interval_secs: std::env::var("SHARE_SWEEP_INTERVAL_SECS")
+                .unwrap_or_else(|_| "300".to_string())
+                .parse()
+                .unwrap_or(300),
+            min_threshold: std::env::var("MPC_MIN_THRESHOLD")
+                .unwrap_or_else(|_| "1".to_string())
+                .parse()
+                .unwrap_or(1),
+            max_total_shares: std::env::var("MPC_MAX_TOTAL_SHARES")
+                .unwrap_or_else(|_| "16".to_string())
+                .parse()
+                .unwrap_or(16),
+            keygen_idempotency_window_secs: std::env::var("MPC_KEYGEN_IDEMPOTENCY_WINDOW_SECS")
+                .unwrap_or_else(|_| "86400".to_string())
+                .parse()
+                .unwrap_or(86400),
+            sign_idempotency_window_secs: std::env::var("MPC_SIGN_IDEMPOTENCY_WINDOW_SECS")
+                .unwrap_or_else(|_| "86400".to_string())
+                .parse()
+                .unwrap_or(86400),
+            signing_session_ttl_secs: std::env::var("MPC_SIGNING_SESSION_TTL_SECS")
+                .unwrap_or_else(|_| "600".to_string())
+                .parse()
+                .unwrap_or(600),
+            policy_file: std::env::var("MPC_POLICY_FILE").ok().filter(|path| !path.is_empty()),
+            audit_log_file: std::env::var("MPC_AUDIT_LOG_FILE").unwrap_or_else(|_| "data/audit.log".to_string()),
         }
     }
-}
\ No newline at end of file
+
+    pub fn is_production(&self) -> bool {
+        self.environment.eq_ignore_ascii_case("production")
+    }
+}
@@ -1,6 +1,7 @@
 use actix_web::{HttpResponse, ResponseError};
 use serde::{Deserialize, Serialize};
-use std::fmt;
+
+use crate::services::frost::FrostError;
 
 #[derive(Debug, thiserror::Error)]
 pub enum MpcError {
@@ -21,6 +22,45 @@ pub enum MpcError {
     
     #[error("Invalid request: {0}")]
     InvalidRequest(String),
+
+    #[error("No participant keys provided")]
+    EmptyKeySet,
+
+    #[error("Duplicate participant key: {0}")]
+    DuplicateKey(String),
+
+    #[error("Invalid partial signature from participant {0}")]
+    InvalidPartialSignature(u32),
+
+    #[error("Participant {0} is not in the signing roster")]
+    ParticipantNotInRoster(u32),
+
+    #[error("Insufficient shares: {provided} provided, threshold is {threshold}")]
+    InsufficientShares { provided: usize, threshold: usize },
+
+    #[error("Key share {0} has expired")]
+    ShareExpired(String),
+
+    #[error("Idempotency key {0} was already used with different parameters")]
+    IdempotencyConflict(String),
+
+    #[error("Solana RPC error: {0}")]
+    RpcError(String),
+
+    #[error("Token lacks required scope: {0}")]
+    InsufficientScope(String),
+
+    #[error("Wallet {0} belongs to another organization")]
+    OrganizationMismatch(String),
+
+    #[error("Wallet {0} is not a required signer of the transaction")]
+    NotARequiredSigner(String),
+
+    #[error("Signing policy violation: {0}")]
+    PolicyViolation(String),
+
+    #[error("Signing session not found: {0}")]
+    SessionNotFound(String),
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -30,18 +70,45 @@ pub struct ErrorResponse {
     pub code: String,
 }
 
-impl ResponseError for MpcError {
-    fn error_response(&self) -> HttpResponse {
-        let (status, code) = match self {
+impl MpcError {
+    fn status_and_code(&self) -> (actix_web::http::StatusCode, &'static str) {
+        match self {
             MpcError::KeyNotFound(_) => (actix_web::http::StatusCode::NOT_FOUND, "KEY_NOT_FOUND"),
             MpcError::InvalidThreshold(_) => (actix_web::http::StatusCode::BAD_REQUEST, "INVALID_THRESHOLD"),
             MpcError::SignatureError(_) => (actix_web::http::StatusCode::INTERNAL_SERVER_ERROR, "SIGNATURE_ERROR"),
             MpcError::AuthError(_) => (actix_web::http::StatusCode::UNAUTHORIZED, "AUTH_ERROR"),
             MpcError::InvalidRequest(_) => (actix_web::http::StatusCode::BAD_REQUEST, "INVALID_REQUEST"),
             MpcError::InternalError(_) => (actix_web::http::StatusCode::INTERNAL_SERVER_ERROR, "INTERNAL_ERROR"),
-        };
+            MpcError::EmptyKeySet => (actix_web::http::StatusCode::BAD_REQUEST, "EMPTY_KEY_SET"),
+            MpcError::DuplicateKey(_) => (actix_web::http::StatusCode::BAD_REQUEST, "DUPLICATE_KEY"),
+            MpcError::InvalidPartialSignature(_) => (actix_web::http::StatusCode::BAD_REQUEST, "INVALID_PARTIAL_SIGNATURE"),
+            MpcError::ParticipantNotInRoster(_) => (actix_web::http::StatusCode::FORBIDDEN, "PARTICIPANT_NOT_IN_ROSTER"),
+            MpcError::InsufficientShares { .. } => (actix_web::http::StatusCode::BAD_REQUEST, "INSUFFICIENT_SHARES"),
+            MpcError::ShareExpired(_) => (actix_web::http::StatusCode::GONE, "SHARE_EXPIRED"),
+            MpcError::IdempotencyConflict(_) => (actix_web::http::StatusCode::CONFLICT, "IDEMPOTENCY_CONFLICT"),
+            MpcError::RpcError(_) => (actix_web::http::StatusCode::BAD_GATEWAY, "RPC_ERROR"),
+            MpcError::InsufficientScope(_) => (actix_web::http::StatusCode::FORBIDDEN, "INSUFFICIENT_SCOPE"),
+            MpcError::OrganizationMismatch(_) => (actix_web::http::StatusCode::FORBIDDEN, "ORGANIZATION_MISMATCH"),
+            MpcError::NotARequiredSigner(_) => (actix_web::http::StatusCode::BAD_REQUEST, "NOT_REQUIRED_SIGNER"),
+            MpcError::PolicyViolation(_) => (actix_web::http::StatusCode::FORBIDDEN, "POLICY_VIOLATION"),
+            MpcError::SessionNotFound(_) => (actix_web::http::StatusCode::NOT_FOUND, "SESSION_NOT_FOUND"),
+        }
+    }
 
-        HttpResponse::build(status).json(ErrorResponse {
+    /// Stable machine-readable error code.
+    pub fn code(&self) -> &'static str {
+        self.status_and_code().1
+    }
+}
+
+impl ResponseError for MpcError {
+    fn status_code(&self) -> actix_web::http::StatusCode {
+        self.status_and_code().0
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let code = self.code();
+        HttpResponse::build(self.status_code()).json(ErrorResponse {
             error: code.to_string(),
             message: self.to_string(),
             code: code.to_string(),
@@ -49,8 +116,40 @@ impl ResponseError for MpcError {
     }
 }
 
+impl From<FrostError> for MpcError {
+    fn from(err: FrostError) -> Self {
+        match err {
+            FrostError::InvalidParameters(reason) => MpcError::InvalidThreshold(reason),
+            FrostError::InsufficientShares { got, need } => MpcError::InsufficientShares { provided: got, threshold: need },
+            other => MpcError::SignatureError(other.to_string()),
+        }
+    }
+}
+
 impl From<anyhow::Error> for MpcError {
     fn from(err: anyhow::Error) -> Self {
         MpcError::InternalError(err.to_string())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_errors_to_status_and_stable_codes() {
+        let cases = [
+            (MpcError::KeyNotFound("w".to_string()), 404, "KEY_NOT_FOUND"),
+            (MpcError::InsufficientShares { provided: 1, threshold: 2 }, 400, "INSUFFICIENT_SHARES"),
+            (MpcError::ShareExpired("s".to_string()), 410, "SHARE_EXPIRED"),
+            (MpcError::SessionNotFound("s".to_string()), 404, "SESSION_NOT_FOUND"),
+            (FrostError::InvalidParameters("t".to_string()).into(), 400, "INVALID_THRESHOLD"),
+        ];
+
+        for (error, status, code) in cases {
+            let response = error.error_response();
+            assert_eq!(response.status().as_u16(), status, "{error}");
+            assert_eq!(error.code(), code);
+        }
+    }
+}
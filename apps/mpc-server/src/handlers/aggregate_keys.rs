@@ -1,15 +1,14 @@
 use actix_web::{web, HttpResponse, Result};
+use crate::middleware::auth::{Claims, SCOPE_KEYGEN};
 use crate::models::*;
 use crate::services::mpc_service::MpcService;
 
 pub async fn aggregate_keys(
     service: web::Data<MpcService>,
+    claims: web::ReqData<Claims>,
     request: web::Json<AggregateKeysRequest>,
 ) -> Result<HttpResponse> {
-    match service.aggregate_keys(request.into_inner()).await {
-        Ok(response) => Ok(HttpResponse::Ok().json(response)),
-        Err(e) => Ok(HttpResponse::BadRequest().json(serde_json::json!({
-            "error": e.to_string()
-        }))),
-    }
+    claims.require_scope(SCOPE_KEYGEN)?;
+    let response = service.aggregate_keys(request.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(response))
 }
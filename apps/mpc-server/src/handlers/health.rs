@@ -1,11 +0,0 @@
-use actix_web::{HttpResponse, Result};
-use serde_json::json;
-
-pub async fn health_check() -> Result<HttpResponse> {
-    Ok(HttpResponse::Ok().json(json!({
-        "status": "healthy",
-        "service": "mpc-server",
-        "timestamp": chrono::Utc::now().to_rfc3339(),
-        "version": env!("CARGO_PKG_VERSION")
-    })))
-}
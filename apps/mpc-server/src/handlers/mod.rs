@@ -1,5 +1,3 @@
-pub mod health;
-pub mod keygen;
 pub mod aggregate_keys;
 pub mod sign_step_one;
 pub mod sign_step_two;
@@ -1,15 +1,41 @@
 use actix_web::{web, HttpResponse, Result};
+use base64::{engine::general_purpose, Engine as _};
+use serde_json::json;
+use std::sync::Arc;
+use crate::middleware::auth::{Claims, SCOPE_SIGN};
 use crate::models::*;
+use crate::services::audit_log::{AuditAction, AuditEvent, AuditLog};
 use crate::services::mpc_service::MpcService;
 
 pub async fn sign_step_one(
     service: web::Data<MpcService>,
+    audit: web::Data<Arc<AuditLog>>,
+    claims: web::ReqData<Claims>,
     request: web::Json<SignStepOneRequest>,
 ) -> Result<HttpResponse> {
-    match service.sign_step_one(request.into_inner()).await {
-        Ok(response) => Ok(HttpResponse::Ok().json(response)),
-        Err(e) => Ok(HttpResponse::BadRequest().json(serde_json::json!({
-            "error": e.to_string()
-        }))),
+    // Opening the session commits every rostered share to nonces
+    let mut event = AuditEvent::new(AuditAction::ShareAccess, claims.sub.clone()).wallet(request.key_id.clone());
+    if let Ok(message) = general_purpose::STANDARD.decode(&request.transaction_data) {
+        event = event.message(&message);
     }
+
+    let authorized = async {
+        claims.require_scope(SCOPE_SIGN)?;
+        let owner = service.wallet_organization(&request.key_id).await?;
+        claims.require_wallet_access(&request.key_id, owner.as_deref())
+    }
+    .await;
+    if let Err(e) = authorized {
+        let event = event.details(json!({ "round": "commit" }));
+        return Err(audit.deny(event, e).into());
+    }
+    let result = service.sign_step_one(request.into_inner()).await;
+    if let Ok(response) = &result {
+        event = event
+            .shares(response.participants.iter().map(|p| p.share_id.clone()).collect())
+            .details(json!({ "signature_id": response.signature_id, "round": "commit" }));
+    }
+
+    let response = audit.record(event, result)?;
+    Ok(HttpResponse::Ok().json(response))
 }
@@ -1,15 +1,33 @@
 use actix_web::{web, HttpResponse, Result};
+use serde_json::json;
+use std::sync::Arc;
+use crate::middleware::auth::{Claims, SCOPE_SIGN};
 use crate::models::*;
+use crate::services::audit_log::{AuditAction, AuditEvent, AuditLog};
 use crate::services::mpc_service::MpcService;
 
 pub async fn sign_step_two(
     service: web::Data<MpcService>,
+    audit: web::Data<Arc<AuditLog>>,
+    claims: web::ReqData<Claims>,
     request: web::Json<SignStepTwoRequest>,
 ) -> Result<HttpResponse> {
-    match service.sign_step_two(request.into_inner()).await {
-        Ok(response) => Ok(HttpResponse::Ok().json(response)),
-        Err(e) => Ok(HttpResponse::BadRequest().json(serde_json::json!({
-            "error": e.to_string()
-        }))),
-    }
+    let denied = || {
+        AuditEvent::new(AuditAction::ShareAccess, claims.sub.clone())
+            .details(json!({ "signature_id": request.signature_id, "round": "sign" }))
+    };
+    claims.require_scope(SCOPE_SIGN).map_err(|e| audit.deny(denied(), e))?;
+    let session = service.session(&request.signature_id).await.map_err(|e| audit.deny(denied(), e))?;
+    let owner = service.wallet_organization(&session.key_id).await?;
+    claims
+        .require_wallet_access(&session.key_id, owner.as_deref())
+        .map_err(|e| audit.deny(denied().wallet(session.key_id.clone()).message(&session.message), e))?;
+
+    let event = AuditEvent::new(AuditAction::ShareAccess, claims.sub.clone())
+        .wallet(session.key_id)
+        .message(&session.message)
+        .shares(session.participants.get(&request.participant_id).cloned().into_iter().collect())
+        .details(json!({ "signature_id": request.signature_id, "round": "sign" }));
+    let response = audit.record(event, service.sign_step_two(request.into_inner()).await)?;
+    Ok(HttpResponse::Ok().json(response))
 }
@@ -3,10 +3,13 @@ use dotenv::dotenv;
 use std::sync::Arc;
 
 mod routes;
+mod handlers;
 mod services;
 mod models;
 mod middleware;
 mod config;
+mod errors;
+mod tls;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
@@ -17,25 +20,152 @@ async fn main() -> std::io::Result<()> {
     let host = config.host.clone();
     let port = config.port;
 
-    log::info!("Starting MPC server on {host}:{port}");
+    log::info!("Starting MPC server");
+
+    // Bearer token verification for /api/mpc
+    let token_verifier = Arc::new(middleware::auth::TokenVerifier::from_config(&config).map_err(|e| {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("Invalid JWT configuration: {e:#}"))
+    })?);
+
+    // Sealed, persistent key share storage
+    let key_manager = open_key_manager(&config)
+        .map_err(|e| std::io::Error::other(format!("Failed to open key store: {e:#}")))?;
+
+    // Hash-chained record of key and signing operations
+    let audit_log = Arc::new(services::audit_log::AuditLog::open(&config.audit_log_file).map_err(|e| {
+        std::io::Error::other(format!("Failed to open audit log: {e:#}"))
+    })?);
+
+    // Periodically drop expired key shares; 0 disables the sweeper
+    let sweeper_metrics = Arc::new(services::expiry_sweeper::SweeperMetrics::default());
+    if config.share_sweep_interval_secs > 0 {
+        services::expiry_sweeper::spawn(
+            key_manager.clone(),
+            std::time::Duration::from_secs(config.share_sweep_interval_secs),
+            sweeper_metrics.clone(),
+            audit_log.clone(),
+        );
+    } else {
+        log::warn!("Key share expiry sweeper disabled (SHARE_SWEEP_INTERVAL_SECS=0)");
+    }
+
+    // Per-wallet limits on what the engine will sign
+    let policies = services::policy::PolicyEngine::from_config(&config).map_err(|e| {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("Invalid signing policy: {e:#}"))
+    })?;
 
     // Create MPC engine
-    let mpc_engine = Arc::new(services::mpc_engine::MpcEngine::new());
+    let mpc_engine = Arc::new(services::mpc_engine::MpcEngine::new(
+        key_manager,
+        // 0 keeps shares until a wallet is deleted explicitly
+        (config.share_retention_hours > 0).then(|| chrono::Duration::hours(config.share_retention_hours as i64)),
+        services::mpc_engine::KeygenLimits {
+            min_threshold: config.min_threshold,
+            max_total_shares: config.max_total_shares,
+        },
+        policies,
+        std::time::Duration::from_secs(config.sign_idempotency_window_secs),
+    ));
+
+    let keygen_idempotency = Arc::new(routes::keygen::KeygenIdempotency::new(
+        std::time::Duration::from_secs(config.keygen_idempotency_window_secs),
+    ));
+
+    let squads_program_id = config.squads_program_id.parse().map_err(|e| {
+        std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("Invalid SQUADS_PROGRAM_ID: {e}"))
+    })?;
 
-    HttpServer::new(move || {
+    // Step-wise signing sessions share the engine's wallets
+    let mpc_service = services::mpc_service::MpcService::new(
+        mpc_engine.clone(),
+        config.solana_rpc_url.clone(),
+        squads_program_id,
+        std::time::Duration::from_secs(config.signing_session_ttl_secs),
+    );
+
+    // mTLS listener: client certificates must chain to the configured CA
+    let tls_config = match &config.tls_cert_file {
+        Some(cert_file) => {
+            let (Some(key_file), Some(client_ca_file)) = (&config.tls_key_file, &config.tls_client_ca_file) else {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "MPC_TLS_CERT_FILE requires MPC_TLS_KEY_FILE and MPC_TLS_CLIENT_CA_FILE",
+                ));
+            };
+            Some(tls::server_config(cert_file, key_file, client_ca_file).map_err(|e| {
+                std::io::Error::new(std::io::ErrorKind::InvalidInput, format!("Invalid TLS configuration: {e:#}"))
+            })?)
+        }
+        None => None,
+    };
+    let http_enabled = config.http_enabled;
+    if !http_enabled && tls_config.is_none() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "MPC_HTTP_ENABLED=false requires an mTLS listener (MPC_TLS_CERT_FILE)",
+        ));
+    }
+    let tls_port = config.tls_port;
+
+    let mut server = HttpServer::new(move || {
         App::new()
             .wrap(Logger::default())
-            .wrap(actix_cors::Cors::permissive())
+            .wrap(cors(&config))
             .app_data(web::Data::new(config.clone()))
             .app_data(web::Data::new(mpc_engine.clone()))
+            .app_data(web::Data::new(mpc_service.clone()))
+            .app_data(web::Data::new(sweeper_metrics.clone()))
+            .app_data(web::Data::new(keygen_idempotency.clone()))
+            .app_data(web::Data::new(audit_log.clone()))
             .service(
                 web::scope("/api/mpc")
+                    .wrap(middleware::auth::AuthMiddleware::new(token_verifier.clone()))
                     .service(routes::keygen::keygen)
                     .service(routes::signing::sign)
+                    .service(routes::transaction::sign_transaction)
+                    .route("/sign/step-one", web::post().to(handlers::sign_step_one::sign_step_one))
+                    .route("/sign/step-two", web::post().to(handlers::sign_step_two::sign_step_two))
+                    .route("/sign/aggregate", web::post().to(handlers::aggregate_signatures::aggregate_signatures))
+                    .route("/aggregate-keys", web::post().to(handlers::aggregate_keys::aggregate_keys))
+                    .route("/balance", web::post().to(handlers::balance::get_balance))
+                    .route("/airdrop", web::post().to(handlers::airdrop::request_airdrop))
+                    .service(routes::audit::verify)
+                    .service(routes::audit::export)
+                    .service(routes::wallets::remove_wallet)
             )
             .service(routes::health::health)
+            .service(routes::metrics::metrics)
     })
-    .bind((host.as_str(), port))?
-    .run()
-    .await
-}
\ No newline at end of file
+    .on_connect(tls::on_connect);
+
+    if http_enabled {
+        log::info!("Listening for HTTP on {host}:{port}");
+        server = server.bind((host.as_str(), port))?;
+    }
+    if let Some(tls_config) = tls_config {
+        log::info!("Listening for mTLS on {host}:{tls_port}");
+        server = server.bind_rustls_0_23((host.as_str(), tls_port), tls_config)?;
+    }
+
+    server.run().await
+}
+
+/// Cross-origin requests are only allowed from `MPC_CORS_ALLOWED_ORIGINS`.
+fn cors(config: &config::Config) -> actix_cors::Cors {
+    config.cors_allowed_origins.iter().fold(
+        actix_cors::Cors::default()
+            .allowed_methods(["GET", "POST"])
+            .allowed_headers([actix_web::http::header::AUTHORIZATION, actix_web::http::header::CONTENT_TYPE])
+            .max_age(3600),
+        |cors, origin| cors.allowed_origin(origin),
+    )
+}
+
+fn open_key_manager(config: &config::Config) -> anyhow::Result<services::key_management::KeyManager> {
+    let cipher = services::share_cipher::ShareCipher::from_config(
+        config.master_key.as_deref(),
+        &config.master_key_file,
+    )?;
+    let store = services::key_store::FileKeyStore::new(&config.key_store_dir)?;
+    services::key_management::KeyManager::new(Arc::new(store), cipher)
+}
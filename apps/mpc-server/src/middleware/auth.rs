@@ -1,20 +1,197 @@
-use actix_web::{dev::ServiceRequest, Error};
-use actix_web::error::ErrorUnauthorized;
+use actix_web::{dev::ServiceRequest, Error, HttpMessage};
+use actix_web::body::EitherBody;
 use actix_web::dev::{ServiceResponse, Transform, Service};
-use jsonwebtoken::{decode, DecodingKey, Validation, Algorithm};
+use jsonwebtoken::{decode, decode_header, DecodingKey, Validation, Algorithm};
 use serde::{Deserialize, Serialize};
-use std::future::{ready, Ready};
+use std::future::{ready, Future, Ready};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::Arc;
 use std::task::{Context, Poll};
 
+use crate::config::Config;
+use crate::errors::MpcError;
+use crate::middleware::jwks::JwksFile;
+use crate::tls::ClientIdentity;
+
+/// HS256 secrets shorter than this are not accepted in production.
+const MIN_PRODUCTION_SECRET_LEN: usize = 32;
+
+pub const SCOPE_KEYGEN: &str = "mpc:keygen";
+pub const SCOPE_SIGN: &str = "mpc:sign";
+pub const SCOPE_AUDIT: &str = "mpc:audit";
+/// Implies every other scope and access to wallets of any organization.
+pub const SCOPE_ADMIN: &str = "mpc:admin";
+
 #[allow(dead_code)]
-#[derive(Debug, Serialize, Deserialize)]
-struct Claims {
-    sub: String,
-    exp: usize,
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: String,
+    pub exp: usize,
+    /// Space-separated scopes, e.g. `"mpc:sign"`.
+    #[serde(default)]
+    pub scope: String,
+    /// Organization the calling service acts for.
+    #[serde(default)]
+    pub org_id: Option<String>,
 }
 
-#[allow(dead_code)]
-pub struct AuthMiddleware;
+impl Claims {
+    fn has_scope(&self, scope: &str) -> bool {
+        self.scope.split_whitespace().any(|s| s == scope || s == SCOPE_ADMIN)
+    }
+
+    pub fn require_scope(&self, scope: &str) -> Result<(), MpcError> {
+        if self.has_scope(scope) {
+            Ok(())
+        } else {
+            Err(MpcError::InsufficientScope(scope.to_string()))
+        }
+    }
+
+    /// Only tokens of the owning organization, or admins, may use a wallet.
+    pub fn require_wallet_access(&self, wallet_id: &str, owner: Option<&str>) -> Result<(), MpcError> {
+        if self.has_scope(SCOPE_ADMIN) {
+            return Ok(());
+        }
+        match (owner, self.org_id.as_deref()) {
+            (Some(owner), Some(org_id)) if owner == org_id => Ok(()),
+            _ => Err(MpcError::OrganizationMismatch(wallet_id.to_string())),
+        }
+    }
+}
+
+/// Token verification settings, as read from the [`Config`].
+#[derive(Default)]
+struct VerifierSettings<'a> {
+    production: bool,
+    insecure_dev_auth: bool,
+    secret: Option<&'a str>,
+    jwks_file: Option<&'a str>,
+    issuer: Option<&'a str>,
+    audience: Option<&'a str>,
+}
+
+/// Verifies bearer tokens: RS256 / EdDSA tokens against the JWKS key named by
+/// their `kid`, HS256 tokens against the shared secret when one is configured.
+/// A configured issuer or audience is required in every token.
+pub struct TokenVerifier {
+    secret: Option<DecodingKey>,
+    jwks: Option<JwksFile>,
+    issuer: Option<String>,
+    audience: Option<String>,
+}
+
+impl TokenVerifier {
+    pub fn new(secret: Option<&str>, jwks: Option<JwksFile>) -> Self {
+        Self {
+            secret: secret.map(|secret| DecodingKey::from_secret(secret.as_bytes())),
+            jwks,
+            issuer: None,
+            audience: None,
+        }
+    }
+
+    /// Builds the verifier from `MPC_JWT_SECRET` / `MPC_JWKS_FILE` and
+    /// `MPC_JWT_ISSUER` / `MPC_JWT_AUDIENCE`. Without a verification key it
+    /// refuses to start unless `MPC_INSECURE_DEV_AUTH` is set outside
+    /// production; production also requires a strong key, issuer and audience.
+    pub fn from_config(config: &Config) -> anyhow::Result<Self> {
+        Self::build(VerifierSettings {
+            production: config.is_production(),
+            insecure_dev_auth: config.insecure_dev_auth,
+            secret: config.jwt_secret.as_deref(),
+            jwks_file: config.jwks_file.as_deref(),
+            issuer: config.jwt_issuer.as_deref(),
+            audience: config.jwt_audience.as_deref(),
+        })
+    }
+
+    fn build(settings: VerifierSettings) -> anyhow::Result<Self> {
+        let VerifierSettings { production, insecure_dev_auth, secret, jwks_file, issuer, audience } = settings;
+        let jwks = jwks_file.map(JwksFile::load).transpose()?;
+        let strong_secret = secret.is_some_and(|secret| secret.len() >= MIN_PRODUCTION_SECRET_LEN);
+
+        if production {
+            if insecure_dev_auth {
+                anyhow::bail!("MPC_INSECURE_DEV_AUTH cannot be enabled in production");
+            }
+            if jwks.is_none() && !strong_secret {
+                anyhow::bail!(
+                    "no JWT verification key configured: set MPC_JWKS_FILE or an MPC_JWT_SECRET of at least {MIN_PRODUCTION_SECRET_LEN} bytes"
+                );
+            }
+            if issuer.is_none() || audience.is_none() {
+                anyhow::bail!("MPC_JWT_ISSUER and MPC_JWT_AUDIENCE are required in production");
+            }
+        }
+
+        let mut verifier = if jwks.is_none() && secret.is_none() {
+            if !insecure_dev_auth {
+                anyhow::bail!("no JWT verification key configured: set MPC_JWKS_FILE or MPC_JWT_SECRET");
+            }
+            log::warn!("MPC_INSECURE_DEV_AUTH is set; accepting HS256 tokens signed with the development secret");
+            Self::new(Some("secret"), None)
+        } else {
+            Self::new(secret, jwks)
+        };
+        verifier.issuer = issuer.map(str::to_string);
+        verifier.audience = audience.map(str::to_string);
+        Ok(verifier)
+    }
+
+    fn verify(&self, token: &str) -> Result<Claims, MpcError> {
+        let header = decode_header(token)
+            .map_err(|e| MpcError::AuthError(format!("Invalid token: {e}")))?;
+
+        let (decoding_key, algorithm) = match (header.alg, &header.kid) {
+            (Algorithm::HS256, _) => (
+                self.secret
+                    .clone()
+                    .ok_or_else(|| MpcError::AuthError("HS256 tokens are not accepted".to_string()))?,
+                Algorithm::HS256,
+            ),
+            (_, Some(kid)) => self.jwks
+                .as_ref()
+                .and_then(|jwks| jwks.key(kid))
+                .ok_or_else(|| MpcError::AuthError(format!("Unknown signing key: {kid}")))?,
+            (_, None) => return Err(MpcError::AuthError("Token has no kid".to_string())),
+        };
+
+        // The algorithm is pinned by the key, never taken from the token alone
+        if header.alg != algorithm {
+            return Err(MpcError::AuthError(format!("Unexpected token algorithm: {:?}", header.alg)));
+        }
+
+        let mut validation = Validation::new(algorithm);
+        let mut required_claims = vec!["exp"];
+        if let Some(issuer) = &self.issuer {
+            validation.set_issuer(&[issuer]);
+            required_claims.push("iss");
+        }
+        if let Some(audience) = &self.audience {
+            validation.set_audience(&[audience]);
+            required_claims.push("aud");
+        }
+        validation.set_required_spec_claims(&required_claims);
+
+        decode::<Claims>(token, &decoding_key, &validation)
+            .map(|token_data| token_data.claims)
+            .map_err(|e| MpcError::AuthError(format!("Invalid token: {e}")))
+    }
+}
+
+/// Rejects requests without a valid `Authorization: Bearer <jwt>` header.
+/// Verified [`Claims`] are stored in the request extensions.
+pub struct AuthMiddleware {
+    verifier: Arc<TokenVerifier>,
+}
+
+impl AuthMiddleware {
+    pub fn new(verifier: Arc<TokenVerifier>) -> Self {
+        Self { verifier }
+    }
+}
 
 impl<S, B> Transform<S, ServiceRequest> for AuthMiddleware
 where
@@ -22,20 +199,23 @@ where
     S::Future: 'static,
     B: 'static,
 {
-    type Response = ServiceResponse<B>;
+    type Response = ServiceResponse<EitherBody<B>>;
     type Error = Error;
     type Transform = AuthMiddlewareService<S>;
     type InitError = ();
     type Future = Ready<Result<Self::Transform, Self::InitError>>;
 
     fn new_transform(&self, service: S) -> Self::Future {
-        ready(Ok(AuthMiddlewareService { service }))
+        ready(Ok(AuthMiddlewareService {
+            service: Rc::new(service),
+            verifier: self.verifier.clone(),
+        }))
     }
 }
 
-#[allow(dead_code)]
 pub struct AuthMiddlewareService<S> {
-    service: S,
+    service: Rc<S>,
+    verifier: Arc<TokenVerifier>,
 }
 
 impl<S, B> Service<ServiceRequest> for AuthMiddlewareService<S>
@@ -44,37 +224,274 @@ where
     S::Future: 'static,
     B: 'static,
 {
-    type Response = ServiceResponse<B>;
+    type Response = ServiceResponse<EitherBody<B>>;
     type Error = Error;
-    // Return the concrete service future type instead of boxing an async block.
-    type Future = S::Future;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
 
     fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
         self.service.poll_ready(cx)
     }
 
     fn call(&self, req: ServiceRequest) -> Self::Future {
-        // direct forwarding of inner service future (no redundant async block)
-        self.service.call(req)
+        let claims = match validate_jwt(&req, &self.verifier) {
+            Ok(claims) => claims,
+            Err(e) => {
+                log::warn!("Rejected {} {}: {e}", req.method(), req.path());
+                let response = req.error_response(e).map_into_right_body();
+                return Box::pin(async move { Ok(response) });
+            }
+        };
+
+        req.extensions_mut().insert(claims);
+        let service = self.service.clone();
+        Box::pin(async move { Ok(service.call(req).await?.map_into_left_body()) })
     }
 }
 
-#[allow(dead_code)]
-async fn validate_jwt(req: &ServiceRequest) -> Result<(), Error> {
+fn validate_jwt(req: &ServiceRequest, verifier: &TokenVerifier) -> Result<Claims, MpcError> {
     let auth_header = req.headers().get("Authorization")
         .and_then(|h| h.to_str().ok())
-        .ok_or_else(|| ErrorUnauthorized("Missing Authorization header"))?;
+        .ok_or_else(|| MpcError::AuthError("Missing Authorization header".to_string()))?;
 
     let token = auth_header.strip_prefix("Bearer ")
-        .ok_or_else(|| ErrorUnauthorized("Invalid Authorization format"))?;
-
-    let secret = std::env::var("MPC_JWT_SECRET").unwrap_or_else(|_| "secret".to_string());
-    
-    decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(secret.as_bytes()),
-        &Validation::new(Algorithm::HS256),
-    ).map_err(|_| ErrorUnauthorized("Invalid token"))?;
-
-    Ok(())
-}
\ No newline at end of file
+        .ok_or_else(|| MpcError::AuthError("Invalid Authorization format".to_string()))?;
+
+    let claims = verifier.verify(token)?;
+
+    // Over mTLS the token must belong to the service holding the certificate
+    if let Some(identity) = req.conn_data::<ClientIdentity>() {
+        if identity.common_name.as_deref() != Some(claims.sub.as_str()) {
+            return Err(MpcError::AuthError(format!(
+                "Token subject {} does not match client certificate {}",
+                claims.sub, identity.subject
+            )));
+        }
+    }
+
+    Ok(claims)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::ErrorResponse;
+    use actix_web::{test, web, App, HttpResponse};
+    use jsonwebtoken::{encode, EncodingKey, Header};
+
+    fn token(secret: &str, exp_offset: i64) -> String {
+        let claims = Claims {
+            sub: "payroll-service".to_string(),
+            exp: (chrono::Utc::now().timestamp() + exp_offset) as usize,
+            scope: SCOPE_SIGN.to_string(),
+            org_id: Some("org-1".to_string()),
+        };
+        encode(&Header::default(), &claims, &EncodingKey::from_secret(secret.as_bytes())).unwrap()
+    }
+
+    #[actix_web::test]
+    async fn rejects_missing_invalid_and_expired_tokens() {
+        let app = test::init_service(
+            App::new().service(
+                web::scope("/api/mpc")
+                    .wrap(AuthMiddleware::new(Arc::new(TokenVerifier::new(Some("test-secret"), None))))
+                    .route("/ping", web::get().to(HttpResponse::Ok)),
+            ),
+        )
+        .await;
+
+        let authorized = [
+            (None, false),
+            (Some("Basic abc".to_string()), false),
+            (Some(format!("Bearer {}", token("other-secret", 3600))), false),
+            (Some(format!("Bearer {}", token("test-secret", -3600))), false),
+            (Some(format!("Bearer {}", token("test-secret", 3600))), true),
+        ];
+
+        for (header, ok) in authorized {
+            let mut req = test::TestRequest::get().uri("/api/mpc/ping");
+            if let Some(header) = &header {
+                req = req.insert_header(("Authorization", header.as_str()));
+            }
+            let resp = test::call_service(&app, req.to_request()).await;
+            if ok {
+                assert!(resp.status().is_success(), "{header:?}");
+            } else {
+                assert_eq!(resp.status(), 401, "{header:?}");
+                let body: ErrorResponse = test::read_body_json(resp).await;
+                assert_eq!(body.code, "AUTH_ERROR");
+            }
+        }
+    }
+
+    /// Serves `/api/mpc/ping` over plain HTTP, attaching a client certificate
+    /// identity with `common_name` to every connection.
+    async fn serve_with_client_identity(common_name: &'static str) -> std::net::SocketAddr {
+        let verifier = Arc::new(TokenVerifier::new(Some("test-secret"), None));
+        let server = actix_web::HttpServer::new(move || {
+            App::new().service(
+                web::scope("/api/mpc")
+                    .wrap(AuthMiddleware::new(verifier.clone()))
+                    .route("/ping", web::get().to(HttpResponse::Ok)),
+            )
+        })
+        .on_connect(move |_, data| {
+            data.insert(ClientIdentity {
+                subject: format!("CN={common_name}"),
+                common_name: Some(common_name.to_string()),
+            });
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let addr = server.addrs()[0];
+        actix_web::rt::spawn(server.run());
+        addr
+    }
+
+    #[actix_web::test]
+    async fn client_certificate_must_match_the_token_subject() {
+        let client = reqwest::Client::new();
+        let bearer = format!("Bearer {}", token("test-secret", 3600));
+
+        let matching = serve_with_client_identity("payroll-service").await;
+        let response = client
+            .get(format!("http://{matching}/api/mpc/ping"))
+            .header("Authorization", &bearer)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+
+        let mismatching = serve_with_client_identity("reporting-service").await;
+        let response = client
+            .get(format!("http://{mismatching}/api/mpc/ping"))
+            .header("Authorization", &bearer)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 401);
+        let body: ErrorResponse = response.json().await.unwrap();
+        assert_eq!(body.code, "AUTH_ERROR");
+        assert!(body.message.contains("CN=reporting-service"), "{}", body.message);
+    }
+
+    #[actix_web::test]
+    async fn scopes_and_organization_gate_access() {
+        let claims = |scope: &str, org_id: Option<&str>| Claims {
+            sub: "svc".to_string(),
+            exp: 0,
+            scope: scope.to_string(),
+            org_id: org_id.map(str::to_string),
+        };
+
+        let payroll = claims("mpc:sign", Some("org-1"));
+        assert!(payroll.require_scope(SCOPE_SIGN).is_ok());
+        assert!(matches!(payroll.require_scope(SCOPE_KEYGEN), Err(MpcError::InsufficientScope(_))));
+        assert!(payroll.require_wallet_access("w", Some("org-1")).is_ok());
+        assert!(matches!(
+            payroll.require_wallet_access("w", Some("org-2")),
+            Err(MpcError::OrganizationMismatch(_))
+        ));
+        assert!(payroll.require_wallet_access("w", None).is_err());
+        assert!(claims("mpc:sign", None).require_wallet_access("w", None).is_err());
+
+        let admin = claims("mpc:admin", None);
+        assert!(admin.require_scope(SCOPE_KEYGEN).is_ok());
+        assert!(admin.require_wallet_access("w", Some("org-2")).is_ok());
+    }
+
+    #[actix_web::test]
+    async fn verifies_eddsa_tokens_by_kid() {
+        use crate::middleware::jwks::tests::{ed25519_key, write_jwks};
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("jwks.json");
+        let (signing_key, jwk) = ed25519_key("auth-1", 1);
+        let (rogue_key, _) = ed25519_key("auth-1", 2);
+        write_jwks(&path, &[jwk]);
+
+        let verifier = TokenVerifier::build(VerifierSettings {
+            jwks_file: Some(path.to_str().unwrap()),
+            ..Default::default()
+        })
+        .unwrap();
+        let claims = Claims {
+            sub: "payroll-service".to_string(),
+            exp: (chrono::Utc::now().timestamp() + 3600) as usize,
+            scope: SCOPE_SIGN.to_string(),
+            org_id: None,
+        };
+        let sign = |kid: Option<&str>, key: &EncodingKey| {
+            let mut header = Header::new(Algorithm::EdDSA);
+            header.kid = kid.map(str::to_string);
+            encode(&header, &claims, key).unwrap()
+        };
+
+        assert_eq!(verifier.verify(&sign(Some("auth-1"), &signing_key)).unwrap().sub, "payroll-service");
+        assert!(verifier.verify(&sign(Some("auth-1"), &rogue_key)).is_err());
+        assert!(verifier.verify(&sign(Some("auth-2"), &signing_key)).is_err());
+        assert!(verifier.verify(&sign(None, &signing_key)).is_err());
+        // No shared secret configured, so HS256 is refused outright
+        assert!(verifier.verify(&token("secret", 3600)).is_err());
+    }
+
+    #[actix_web::test]
+    async fn refuses_to_start_without_a_real_verification_key() {
+        let strong = "k".repeat(32);
+        let production = |secret: Option<&'static str>| VerifierSettings {
+            production: true,
+            secret,
+            issuer: Some("auth-service"),
+            audience: Some("mpc-server"),
+            ..Default::default()
+        };
+
+        assert!(TokenVerifier::build(production(None)).is_err());
+        assert!(TokenVerifier::build(production(Some("secret"))).is_err());
+        assert!(TokenVerifier::build(VerifierSettings { secret: Some(&strong), ..production(None) }).is_ok());
+        assert!(TokenVerifier::build(VerifierSettings { secret: Some(&strong), issuer: None, ..production(None) }).is_err());
+        assert!(TokenVerifier::build(VerifierSettings { secret: Some(&strong), audience: None, ..production(None) }).is_err());
+        assert!(TokenVerifier::build(VerifierSettings { insecure_dev_auth: true, ..production(None) }).is_err());
+
+        // Outside production the development secret needs an explicit opt-in
+        assert!(TokenVerifier::build(VerifierSettings::default()).is_err());
+        let insecure = TokenVerifier::build(VerifierSettings { insecure_dev_auth: true, ..Default::default() }).unwrap();
+        assert!(insecure.verify(&token("secret", 3600)).is_ok());
+    }
+
+    #[actix_web::test]
+    async fn requires_the_configured_issuer_and_audience() {
+        let verifier = TokenVerifier::build(VerifierSettings {
+            secret: Some("test-secret"),
+            issuer: Some("auth-service"),
+            audience: Some("mpc-server"),
+            ..Default::default()
+        })
+        .unwrap();
+
+        #[derive(Serialize)]
+        struct IssuedClaims<'a> {
+            sub: &'a str,
+            exp: i64,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            iss: Option<&'a str>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            aud: Option<&'a str>,
+        }
+        let sign = |iss: Option<&str>, aud: Option<&str>| {
+            let claims = IssuedClaims {
+                sub: "payroll-service",
+                exp: chrono::Utc::now().timestamp() + 3600,
+                iss,
+                aud,
+            };
+            encode(&Header::default(), &claims, &EncodingKey::from_secret(b"test-secret")).unwrap()
+        };
+
+        assert!(verifier.verify(&sign(Some("auth-service"), Some("mpc-server"))).is_ok());
+        assert!(verifier.verify(&sign(Some("rogue"), Some("mpc-server"))).is_err());
+        assert!(verifier.verify(&sign(Some("auth-service"), Some("other-service"))).is_err());
+        assert!(verifier.verify(&sign(None, Some("mpc-server"))).is_err());
+        assert!(verifier.verify(&sign(Some("auth-service"), None)).is_err());
+    }
+}
@@ -0,0 +1,159 @@
+use anyhow::{Context, Result};
+use jsonwebtoken::jwk::{AlgorithmParameters, JwkSet, KeyAlgorithm};
+use jsonwebtoken::{Algorithm, DecodingKey};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often the JWKS file is checked for changes.
+const RELOAD_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+/// RS256 / EdDSA verification keys from a local JWKS file, selected by `kid`.
+/// The file is reloaded when its modification time or size changes; a file
+/// that fails to parse leaves the previous keys in place.
+pub struct JwksFile {
+    path: PathBuf,
+    check_interval: Duration,
+    state: RwLock<JwksState>,
+}
+
+struct JwksState {
+    keys: HashMap<String, (DecodingKey, Algorithm)>,
+    fingerprint: Option<(SystemTime, u64)>,
+    checked_at: Instant,
+}
+
+impl JwksFile {
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self> {
+        Self::with_check_interval(path, RELOAD_CHECK_INTERVAL)
+    }
+
+    fn with_check_interval(path: impl Into<PathBuf>, check_interval: Duration) -> Result<Self> {
+        let path = path.into();
+        let fingerprint = fingerprint(&path);
+        let keys = read_keys(&path)?;
+        if keys.is_empty() {
+            anyhow::bail!("JWKS file {} contains no usable RS256 or EdDSA keys", path.display());
+        }
+        log::info!("Loaded {} JWT verification keys from {}", keys.len(), path.display());
+
+        Ok(Self {
+            path,
+            check_interval,
+            state: RwLock::new(JwksState {
+                keys,
+                fingerprint,
+                checked_at: Instant::now(),
+            }),
+        })
+    }
+
+    /// Verification key and algorithm registered under `kid`.
+    pub fn key(&self, kid: &str) -> Option<(DecodingKey, Algorithm)> {
+        self.reload_if_changed();
+        self.state.read().unwrap().keys.get(kid).cloned()
+    }
+
+    fn reload_if_changed(&self) {
+        if self.state.read().unwrap().checked_at.elapsed() < self.check_interval {
+            return;
+        }
+
+        let mut state = self.state.write().unwrap();
+        state.checked_at = Instant::now();
+        let current = fingerprint(&self.path);
+        if current == state.fingerprint {
+            return;
+        }
+        state.fingerprint = current;
+
+        match read_keys(&self.path) {
+            Ok(keys) => {
+                log::info!("Reloaded {} JWT verification keys from {}", keys.len(), self.path.display());
+                state.keys = keys;
+            }
+            Err(e) => log::error!("Keeping previous JWT verification keys: {e:#}"),
+        }
+    }
+}
+
+fn fingerprint(path: &PathBuf) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+fn read_keys(path: &PathBuf) -> Result<HashMap<String, (DecodingKey, Algorithm)>> {
+    let contents = fs::read(path).with_context(|| format!("reading JWKS file {}", path.display()))?;
+    let jwks: JwkSet = serde_json::from_slice(&contents)
+        .with_context(|| format!("parsing JWKS file {}", path.display()))?;
+
+    let mut keys = HashMap::new();
+    for jwk in &jwks.keys {
+        let Some(kid) = jwk.common.key_id.clone() else {
+            log::warn!("Skipping JWKS key without kid");
+            continue;
+        };
+        let algorithm = match (&jwk.algorithm, jwk.common.key_algorithm) {
+            (AlgorithmParameters::RSA(_), None | Some(KeyAlgorithm::RS256)) => Algorithm::RS256,
+            (AlgorithmParameters::OctetKeyPair(_), None | Some(KeyAlgorithm::EdDSA)) => Algorithm::EdDSA,
+            _ => {
+                log::warn!("Skipping JWKS key {kid}: only RS256 and EdDSA keys are accepted");
+                continue;
+            }
+        };
+        let key = DecodingKey::from_jwk(jwk).with_context(|| format!("invalid JWKS key {kid}"))?;
+        keys.insert(kid, (key, algorithm));
+    }
+    Ok(keys)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use base64::{engine::general_purpose, Engine as _};
+    use ed25519_dalek::SigningKey;
+    use jsonwebtoken::EncodingKey;
+
+    /// Ed25519 signing key for `seed` as a jsonwebtoken key and a JWK entry.
+    pub(crate) fn ed25519_key(kid: &str, seed: u8) -> (EncodingKey, serde_json::Value) {
+        let signing_key = SigningKey::from_bytes(&[seed; 32]);
+        // PKCS#8 v1 wrapping of the raw Ed25519 seed
+        let mut der = vec![
+            0x30, 0x2e, 0x02, 0x01, 0x00, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x04, 0x22, 0x04, 0x20,
+        ];
+        der.extend_from_slice(&signing_key.to_bytes());
+
+        let jwk = serde_json::json!({
+            "kty": "OKP",
+            "crv": "Ed25519",
+            "alg": "EdDSA",
+            "kid": kid,
+            "x": general_purpose::URL_SAFE_NO_PAD.encode(signing_key.verifying_key().as_bytes()),
+        });
+        (EncodingKey::from_ed_der(&der), jwk)
+    }
+
+    pub(crate) fn write_jwks(path: &std::path::Path, keys: &[serde_json::Value]) {
+        fs::write(path, serde_json::json!({ "keys": keys }).to_string()).unwrap();
+    }
+
+    #[test]
+    fn selects_by_kid_and_reloads_on_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("jwks.json");
+        write_jwks(&path, &[ed25519_key("k1", 1).1]);
+
+        let jwks = JwksFile::with_check_interval(&path, Duration::ZERO).unwrap();
+        assert!(matches!(jwks.key("k1"), Some((_, Algorithm::EdDSA))));
+        assert!(jwks.key("k2").is_none());
+
+        write_jwks(&path, &[ed25519_key("k1", 1).1, ed25519_key("k2", 2).1]);
+        assert!(jwks.key("k2").is_some());
+
+        // A broken rewrite keeps the last good key set
+        fs::write(&path, "not json").unwrap();
+        assert!(jwks.key("k2").is_some());
+    }
+}
@@ -1,6 +1,10 @@
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use uuid::Uuid;
 
+use crate::services::frost::{Identifier, SigningNonces, SigningPackage};
+use crate::services::policy::Spend;
+
 #[allow(dead_code)]
 #[derive(Debug, Serialize, Deserialize)]
 pub struct KeygenRequest {
@@ -40,41 +44,184 @@ pub struct HealthResponse {
     pub timestamp: String,
 }
 
-#[allow(dead_code)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct KeyShare {
     pub id: String,
     pub wallet_id: String,
     pub share_data: Vec<u8>,
     pub created_at: chrono::DateTime<chrono::Utc>,
-    pub expires_at: chrono::DateTime<chrono::Utc>,
+    /// `None` for shares that never expire.
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 impl KeyShare {
-    #[allow(dead_code)]
-    pub fn new(wallet_id: String, share_data: Vec<u8>, retention_hours: u64) -> Self {
+    pub fn new(wallet_id: String, share_data: Vec<u8>, retention: Option<chrono::Duration>) -> Self {
         let now = chrono::Utc::now();
         Self {
             id: Uuid::new_v4().to_string(),
             wallet_id,
             share_data,
             created_at: now,
-            expires_at: now + chrono::Duration::hours(retention_hours as i64),
+            expires_at: retention.map(|retention| now + retention),
         }
     }
     
-    #[allow(dead_code)]
     pub fn is_expired(&self) -> bool {
-        chrono::Utc::now() > self.expires_at
+        self.expires_at.is_some_and(|expires_at| chrono::Utc::now() > expires_at)
     }
 }
 
-#[allow(dead_code)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WalletInfo {
     pub wallet_id: String,
     pub public_key: String,
+    pub verifying_shares: BTreeMap<Identifier, String>, // base64 encoded, per participant
     pub threshold: u32,
     pub total_shares: u32,
+    /// Organization whose tokens may sign with this wallet; `None` for
+    /// wallets only reachable with the `mpc:admin` scope.
+    #[serde(default)]
+    pub organization_id: Option<String>,
+    /// Caller-scoped keygen `request_id` that created the wallet, so retries
+    /// are recognised across restarts.
+    #[serde(default)]
+    pub keygen_request: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AggregateKeysRequest {
+    pub participant_keys: Vec<String>,
+    /// Base64 of 32 secret random bytes from which, with the members and
+    /// threshold, the Squads `create_key` is derived. Never reuse it.
+    pub create_key_salt: String,
+    /// Approvals required; defaults to every participant.
+    #[serde(default)]
+    pub threshold: Option<u16>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AggregateKeysResponse {
+    pub aggregated_address: String, // Squads vault 0, the address that holds funds
+    pub multisig_address: String,
+    pub create_key: String, // signs `multisig_create_v2`; seeds the multisig address
+    pub members: Vec<String>, // sorted participant keys to create the multisig with
+    pub threshold: u16,
+    pub participant_count: u32,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SignStepOneRequest {
+    pub key_id: String,
+    pub transaction_data: String, // base64 encoded message to sign
+    #[serde(default)]
+    pub share_ids: Vec<String>, // signing roster, defaults to every share of the key
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SessionParticipant {
+    pub participant_id: u32,
+    pub share_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SignStepOneResponse {
+    pub signature_id: String,
+    pub key_id: String,
+    pub transaction_data: String,
+    pub participants: Vec<SessionParticipant>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SignStepTwoRequest {
+    pub signature_id: String,
+    pub participant_id: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SignStepTwoResponse {
+    pub signature_id: String,
+    pub partial_signature: String,
+    pub participant_id: u32,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PartialSignature {
+    pub participant_id: u32,
+    pub signature: String, // bs58 encoded signature share
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AggregateSignaturesRequest {
+    pub signature_id: String,
+    pub partial_signatures: Vec<PartialSignature>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AggregateSignaturesResponse {
+    pub signature_id: String,
+    pub final_signature: String,
+    pub transaction_id: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BalanceRequest {
+    pub address: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BalanceResponse {
+    pub address: String,
+    pub balance: u64,
+    pub lamports: u64,
+    pub sol: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AirdropRequest {
+    pub address: String,
+    pub amount: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AirdropResponse {
+    pub address: String,
+    pub amount: u64,
+    pub transaction_id: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SignatureStatus {
+    Pending,
+    Partial,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct PartialSignatureData {
+    pub participant_id: u32,
+    pub signature: Vec<u8>,
     pub created_at: chrono::DateTime<chrono::Utc>,
-}
\ No newline at end of file
+}
+
+/// A step-wise signing session. Holds the round-one nonces of every
+/// participant until they produce their signature share; the session is
+/// dropped once aggregated, once aggregation fails, or when it expires.
+#[allow(dead_code)]
+pub struct MpcSignature {
+    pub id: Uuid,
+    pub key_id: Uuid,
+    pub message: Vec<u8>,
+    pub signing_package: SigningPackage,
+    pub participants: BTreeMap<Identifier, String>, // participant id -> share id
+    pub nonces: HashMap<Identifier, SigningNonces>,
+    pub partial_signatures: Vec<PartialSignatureData>,
+    pub status: SignatureStatus,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    /// Daily cap spend of the message, returned if no signature comes of it.
+    pub spend: Option<Spend>,
+}
@@ -0,0 +1,26 @@
+use actix_web::{get, web, HttpResponse};
+use std::sync::Arc;
+use crate::errors::MpcError;
+use crate::middleware::auth::{Claims, SCOPE_AUDIT};
+use crate::services::audit_log::AuditLog;
+
+/// Checks the audit log hash chain.
+#[get("/audit/verify")]
+pub async fn verify(
+    audit: web::Data<Arc<AuditLog>>,
+    claims: web::ReqData<Claims>,
+) -> Result<HttpResponse, MpcError> {
+    claims.require_scope(SCOPE_AUDIT)?;
+    Ok(HttpResponse::Ok().json(audit.verify()))
+}
+
+/// The audit log as JSON lines, one entry per line.
+#[get("/audit/export")]
+pub async fn export(
+    audit: web::Data<Arc<AuditLog>>,
+    claims: web::ReqData<Claims>,
+) -> Result<HttpResponse, MpcError> {
+    claims.require_scope(SCOPE_AUDIT)?;
+    let entries = audit.export()?;
+    Ok(HttpResponse::Ok().content_type("application/x-ndjson").body(entries))
+}
@@ -1,18 +1,26 @@
-use actix_web::{post, web, HttpResponse, Responder};
+use actix_web::{post, web, HttpResponse};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use std::time::Duration;
 use base64::{engine::general_purpose, Engine as _};
+use crate::errors::MpcError;
+use crate::middleware::auth::{Claims, SCOPE_KEYGEN};
+use crate::services::audit_log::{AuditAction, AuditEvent, AuditLog};
+use crate::services::idempotency::IdempotencyCache;
 use crate::services::mpc_engine::MpcEngine;
 
+/// Keygen results by caller and `request_id`, parameterised by
+/// `(threshold, total_shares)`.
+pub type KeygenIdempotency = IdempotencyCache<(usize, usize), KeygenResponse>;
+
 #[derive(Deserialize)]
 pub struct KeygenRequest {
     pub threshold: usize,
     pub total_shares: usize,
-    #[allow(dead_code)]
     pub request_id: String,
 }
 
-#[derive(Serialize)]
+#[derive(Clone, Serialize)]
 pub struct KeygenResponse {
     pub wallet_id: String,
     pub public_key: String,
@@ -24,19 +32,86 @@ pub struct KeygenResponse {
 pub async fn keygen(
     req: web::Json<KeygenRequest>,
     engine: web::Data<Arc<MpcEngine>>,
-) -> impl Responder {
-    match engine.generate_key(req.threshold, req.total_shares) {
-        Ok(wallet) => {
-            let response = KeygenResponse {
-                wallet_id: wallet.wallet_id,
-                public_key: general_purpose::STANDARD.encode(wallet.public_key.as_bytes()),
-                share_ids: wallet.shares.iter().map(|s| s.share_id.clone()).collect(),
-                threshold: wallet.threshold,
-            };
-            HttpResponse::Ok().json(response)
+    claims: web::ReqData<Claims>,
+    idempotency: web::Data<Arc<KeygenIdempotency>>,
+    audit: web::Data<Arc<AuditLog>>,
+) -> Result<HttpResponse, MpcError> {
+    claims.require_scope(SCOPE_KEYGEN).map_err(|e| {
+        let event = AuditEvent::new(AuditAction::Keygen, claims.sub.clone())
+            .details(serde_json::json!({ "request_id": req.request_id }));
+        audit.deny(event, e)
+    })?;
+
+    // A retried request_id returns the wallet created by the first attempt,
+    // found in the key store when the retry follows a restart
+    let caller = claims.org_id.as_deref().unwrap_or(&claims.sub);
+    let key = format!("{caller}:{}", req.request_id);
+    let params = (req.threshold, req.total_shares);
+    let result = match persisted_keygen(&engine, &key, params, idempotency.window()).await {
+        Some(result) => result,
+        None => {
+            idempotency
+                .run(key.clone(), params, || async {
+                    // The new wallet belongs to the organization of the calling token
+                    let wallet = engine
+                        .generate_key(
+                            req.threshold,
+                            req.total_shares,
+                            claims.org_id.clone(),
+                            Some(key.clone()),
+                        )
+                        .await?;
+
+                    Ok(KeygenResponse {
+                        wallet_id: wallet.wallet_id,
+                        public_key: general_purpose::STANDARD.encode(wallet.public_key.as_bytes()),
+                        share_ids: wallet.shares.iter().map(|s| s.share_id.clone()).collect(),
+                        threshold: wallet.threshold,
+                    })
+                })
+                .await
         }
-        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": e
-        })),
+    };
+
+    let mut event = AuditEvent::new(AuditAction::Keygen, claims.sub.clone()).details(serde_json::json!({
+        "request_id": req.request_id,
+        "threshold": req.threshold,
+        "total_shares": req.total_shares,
+    }));
+    if let Ok((response, replayed)) = &result {
+        event = event
+            .wallet(response.wallet_id.clone())
+            .shares(response.share_ids.clone())
+            .replayed(*replayed);
     }
-}
\ No newline at end of file
+
+    let (response, _) = audit.record(event, result)?;
+    Ok(HttpResponse::Ok().json(response))
+}
+
+/// Replays a keygen whose wallet was persisted within the idempotency window.
+async fn persisted_keygen(
+    engine: &MpcEngine,
+    key: &str,
+    params: (usize, usize),
+    window: Duration,
+) -> Option<Result<(KeygenResponse, bool), MpcError>> {
+    let wallet = engine.wallet_for_keygen_request(key).await?;
+    let age = (chrono::Utc::now() - wallet.created_at).to_std().unwrap_or_default();
+    if age >= window {
+        return None;
+    }
+    if (wallet.threshold as usize, wallet.total_shares as usize) != params {
+        return Some(Err(MpcError::IdempotencyConflict(key.to_string())));
+    }
+
+    // A wallet without shares was never completely created; run keygen again
+    let share_ids = engine.share_ids(&wallet.wallet_id).await.ok().filter(|ids| !ids.is_empty())?;
+    let response = KeygenResponse {
+        wallet_id: wallet.wallet_id,
+        public_key: wallet.public_key,
+        share_ids,
+        threshold: wallet.threshold as usize,
+    };
+    Some(Ok((response, true)))
+}
@@ -0,0 +1,11 @@
+use actix_web::{get, web, HttpResponse, Responder};
+use std::sync::Arc;
+
+use crate::services::expiry_sweeper::SweeperMetrics;
+
+#[get("/metrics")]
+pub async fn metrics(sweeper: web::Data<Arc<SweeperMetrics>>) -> impl Responder {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(sweeper.render())
+}
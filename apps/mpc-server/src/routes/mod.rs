@@ -1,4 +1,7 @@
+pub mod audit;
 pub mod keygen;
 pub mod signing;
+pub mod transaction;
+pub mod wallets;
 pub mod health;
-
+pub mod metrics;
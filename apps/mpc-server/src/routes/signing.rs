@@ -1,7 +1,12 @@
-use actix_web::{post, web, HttpResponse, Responder};
+use actix_web::{post, web, HttpResponse};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use base64::{engine::general_purpose, Engine as _};
+use ed25519_dalek::Signature;
+use serde_json::json;
+use crate::errors::MpcError;
+use crate::middleware::auth::{Claims, SCOPE_SIGN};
+use crate::services::audit_log::{AuditAction, AuditEvent, AuditLog};
 use crate::services::mpc_engine::MpcEngine;
 
 #[derive(Deserialize)]
@@ -9,6 +14,9 @@ pub struct SignRequest {
     pub wallet_id: String,
     pub message: String, // base64 encoded
     pub share_ids: Vec<String>,
+    /// Retries with the same key and message return the original signature.
+    #[serde(default)]
+    pub idempotency_key: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -17,27 +25,118 @@ pub struct SignResponse {
     pub public_key: String,
 }
 
+/// Coordinates FROST signing: collects nonce commitments from the selected
+/// shares, gathers their signature shares and returns the combined signature.
 #[post("/sign")]
 pub async fn sign(
     req: web::Json<SignRequest>,
     engine: web::Data<Arc<MpcEngine>>,
-) -> impl Responder {
-    let message = match general_purpose::STANDARD.decode(&req.message) {
-        Ok(m) => m,
-        Err(e) => return HttpResponse::BadRequest().json(serde_json::json!({
-            "error": format!("Invalid base64 message: {}", e)
-        })),
+    audit: web::Data<Arc<AuditLog>>,
+    claims: web::ReqData<Claims>,
+) -> Result<HttpResponse, MpcError> {
+    authorize_signing(&engine, &audit, &claims, &req.wallet_id).await?;
+
+    let message = general_purpose::STANDARD
+        .decode(&req.message)
+        .map_err(|e| MpcError::InvalidRequest(format!("Invalid base64 message: {e}")))?;
+
+    let signature = sign_and_audit(
+        &engine,
+        &audit,
+        &claims,
+        &req.wallet_id,
+        &message,
+        req.share_ids.clone(),
+        req.idempotency_key.as_deref(),
+    )
+    .await?;
+
+    Ok(HttpResponse::Ok().json(SignResponse {
+        signature: general_purpose::STANDARD.encode(signature.to_bytes()),
+        public_key: req.wallet_id.clone(),
+    }))
+}
+
+/// Requires the `mpc:sign` scope and access to the wallet's organization.
+/// Denials are recorded as failed `sign` entries.
+pub(crate) async fn authorize_signing(
+    engine: &MpcEngine,
+    audit: &AuditLog,
+    claims: &Claims,
+    wallet_id: &str,
+) -> Result<(), MpcError> {
+    let result = async {
+        claims.require_scope(SCOPE_SIGN)?;
+        let owner = engine.wallet_organization(wallet_id).await?;
+        claims.require_wallet_access(wallet_id, owner.as_deref())
+    }
+    .await;
+    result.map_err(|e| audit.deny(AuditEvent::new(AuditAction::Sign, claims.sub.clone()).wallet(wallet_id), e))
+}
+
+/// Signs `message`, deduplicated by `idempotency_key` when given, and records
+/// the attempt in the audit log along with any decoded payroll instructions.
+pub(crate) async fn sign_and_audit(
+    engine: &MpcEngine,
+    audit: &AuditLog,
+    claims: &Claims,
+    wallet_id: &str,
+    message: &[u8],
+    share_ids: Vec<String>,
+    idempotency_key: Option<&str>,
+) -> Result<Signature, MpcError> {
+    let event = AuditEvent::new(AuditAction::Sign, claims.sub.clone())
+        .wallet(wallet_id)
+        .message(message)
+        .shares(share_ids.clone())
+        .details(json!({
+            "idempotency_key": idempotency_key,
+            "payroll": engine.describe_message(message),
+        }));
+
+    let result = match idempotency_key {
+        Some(key) => engine.sign_message_idempotent(wallet_id, message, share_ids, key).await,
+        None => engine.sign_message(wallet_id, message, share_ids).await.map(|signature| (signature, false)),
     };
+    // A replay is recorded as such, not as a new signing event
+    let replayed = matches!(result, Ok((_, true)));
+    audit.record(event.replayed(replayed), result).map(|(signature, _)| signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::audit_log::AuditOutcome;
+    use crate::services::mpc_engine::tests::engine;
 
-    match engine.sign_message(&req.wallet_id, &message, req.share_ids.clone()) {
-        Ok(signature) => {
-            HttpResponse::Ok().json(SignResponse {
-                signature: general_purpose::STANDARD.encode(signature.to_bytes()),
-                public_key: req.wallet_id.clone(),
-            })
+    #[tokio::test]
+    async fn denied_signing_requests_are_audited() {
+        let dir = tempfile::tempdir().unwrap();
+        let audit = AuditLog::open(dir.path().join("audit.log")).unwrap();
+        let engine = engine();
+        let wallet = engine.generate_key(2, 3, Some("org-a".to_string()), None).await.unwrap();
+        let claims = |scope: &str, org_id: &str| Claims {
+            sub: "payroll-service".to_string(),
+            exp: usize::MAX,
+            scope: scope.to_string(),
+            org_id: Some(org_id.to_string()),
+        };
+
+        let wallet_id = &wallet.wallet_id;
+        let missing_scope = authorize_signing(&engine, &audit, &claims("mpc:keygen", "org-a"), wallet_id).await;
+        assert!(matches!(missing_scope, Err(MpcError::InsufficientScope(_))));
+        let other_org = authorize_signing(&engine, &audit, &claims(SCOPE_SIGN, "org-b"), wallet_id).await;
+        assert!(matches!(other_org, Err(MpcError::OrganizationMismatch(_))));
+        authorize_signing(&engine, &audit, &claims(SCOPE_SIGN, "org-a"), wallet_id).await.unwrap();
+
+        let export = String::from_utf8(audit.export().unwrap()).unwrap();
+        let entries: Vec<serde_json::Value> = export.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(entries.len(), 2);
+        for (entry, code) in entries.iter().zip(["INSUFFICIENT_SCOPE", "ORGANIZATION_MISMATCH"]) {
+            assert_eq!(entry["action"], "sign");
+            assert_eq!(entry["outcome"], serde_json::json!(AuditOutcome::Failure));
+            assert_eq!(entry["wallet_id"], *wallet_id);
+            assert!(entry["error"].as_str().unwrap().starts_with(code));
         }
-        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": e
-        })),
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,65 @@
+use actix_web::{post, web, HttpResponse};
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use std::sync::Arc;
+use crate::errors::MpcError;
+use crate::middleware::auth::Claims;
+use crate::routes::signing::{authorize_signing, sign_and_audit};
+use crate::services::audit_log::AuditLog;
+use crate::services::mpc_engine::MpcEngine;
+use crate::services::solana_tx;
+
+#[derive(Deserialize)]
+pub struct SignTransactionRequest {
+    pub wallet_id: String,
+    pub transaction: String, // base64 encoded, bincode-serialized
+    pub share_ids: Vec<String>,
+    #[serde(default)]
+    pub idempotency_key: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct SignTransactionResponse {
+    pub transaction: String, // base64 encoded, with the wallet's signature applied
+    pub signature: String,   // base58 encoded
+    pub signer_index: usize,
+    /// Required signatures other signers still have to provide.
+    pub missing_signatures: usize,
+}
+
+/// Signs a serialized legacy or versioned Solana transaction with the wallet
+/// key and returns it with the signature in the wallet's signer slot.
+#[post("/sign/transaction")]
+pub async fn sign_transaction(
+    req: web::Json<SignTransactionRequest>,
+    engine: web::Data<Arc<MpcEngine>>,
+    audit: web::Data<Arc<AuditLog>>,
+    claims: web::ReqData<Claims>,
+) -> Result<HttpResponse, MpcError> {
+    authorize_signing(&engine, &audit, &claims, &req.wallet_id).await?;
+
+    let mut transaction = solana_tx::decode_transaction(&req.transaction)?;
+    let (public_key, _) = engine.public_keys(&req.wallet_id).await?;
+    let signer = Pubkey::new_from_array(public_key.to_bytes());
+    let signer_index = solana_tx::signer_index(&transaction, &signer)?;
+
+    let signature = sign_and_audit(
+        &engine,
+        &audit,
+        &claims,
+        &req.wallet_id,
+        &transaction.message.serialize(),
+        req.share_ids.clone(),
+        req.idempotency_key.as_deref(),
+    )
+    .await?;
+    let signature = solana_sdk::signature::Signature::from(signature.to_bytes());
+    solana_tx::apply_signature(&mut transaction, signer_index, signature);
+
+    Ok(HttpResponse::Ok().json(SignTransactionResponse {
+        transaction: solana_tx::encode_transaction(&transaction)?,
+        signature: signature.to_string(),
+        signer_index,
+        missing_signatures: solana_tx::missing_signatures(&transaction),
+    }))
+}
@@ -0,0 +1,36 @@
+use actix_web::{delete, web, HttpResponse};
+use serde::Serialize;
+use std::sync::Arc;
+use crate::errors::MpcError;
+use crate::middleware::auth::{Claims, SCOPE_ADMIN};
+use crate::services::audit_log::{AuditAction, AuditEvent, AuditLog};
+use crate::services::mpc_engine::MpcEngine;
+
+#[derive(Serialize)]
+pub struct RemoveWalletResponse {
+    pub wallet_id: String,
+    pub share_ids: Vec<String>,
+}
+
+/// Permanently deletes a wallet and its key shares. Admin only.
+#[delete("/wallets/{wallet_id}")]
+pub async fn remove_wallet(
+    path: web::Path<String>,
+    engine: web::Data<Arc<MpcEngine>>,
+    audit: web::Data<Arc<AuditLog>>,
+    claims: web::ReqData<Claims>,
+) -> Result<HttpResponse, MpcError> {
+    let wallet_id = path.into_inner();
+    claims.require_scope(SCOPE_ADMIN).map_err(|e| {
+        audit.deny(AuditEvent::new(AuditAction::WalletRemoved, claims.sub.clone()).wallet(wallet_id.clone()), e)
+    })?;
+
+    let result = engine.remove_wallet(&wallet_id).await;
+    let mut event = AuditEvent::new(AuditAction::WalletRemoved, claims.sub.clone()).wallet(wallet_id.clone());
+    if let Ok(share_ids) = &result {
+        event = event.shares(share_ids.clone());
+    }
+
+    let share_ids = audit.record(event, result)?;
+    Ok(HttpResponse::Ok().json(RemoveWalletResponse { wallet_id, share_ids }))
+}
@@ -0,0 +1,349 @@
+//! Append-only, hash-chained audit log.
+//!
+//! Every entry carries the hash of the one before it, so an edited, removed
+//! or reordered entry breaks the chain and the server refuses to start on it.
+//! Dropping entries off the end while the server is stopped leaves a valid,
+//! shorter chain and is out of scope here: the server keeps no head outside
+//! the log to compare against. Operators who need that guarantee must record
+//! `head_hash` and `entries` from `/audit/verify` (or the head logged at
+//! startup) somewhere the server cannot write, and compare them after a
+//! restart.
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::errors::MpcError;
+
+/// `prev_hash` of the first entry.
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    Keygen,
+    Sign,
+    ShareAccess,
+    ShareRemoved,
+    WalletRemoved,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOutcome {
+    Success,
+    /// Answered from an idempotency cache without repeating the operation.
+    Replayed,
+    Failure,
+}
+
+/// One audited operation. The hash covers every field, including the hash
+/// of the previous entry.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub seq: u64,
+    pub timestamp: DateTime<Utc>,
+    pub action: AuditAction,
+    pub caller: String,
+    pub wallet_id: Option<String>,
+    pub message_sha256: Option<String>,
+    pub share_ids: Vec<String>,
+    pub outcome: AuditOutcome,
+    pub error: Option<String>,
+    pub details: Option<Value>,
+    pub prev_hash: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    #[serde(flatten)]
+    pub record: AuditRecord,
+    pub hash: String,
+}
+
+impl AuditRecord {
+    fn hash(&self) -> String {
+        let bytes = serde_json::to_vec(self).expect("audit records serialize");
+        hex::encode(Sha256::digest(bytes))
+    }
+}
+
+/// Operation to record, filled in by the code performing it.
+pub struct AuditEvent {
+    action: AuditAction,
+    caller: String,
+    wallet_id: Option<String>,
+    message_sha256: Option<String>,
+    share_ids: Vec<String>,
+    replayed: bool,
+    details: Option<Value>,
+}
+
+impl AuditEvent {
+    pub fn new(action: AuditAction, caller: impl Into<String>) -> Self {
+        Self {
+            action,
+            caller: caller.into(),
+            wallet_id: None,
+            message_sha256: None,
+            share_ids: Vec::new(),
+            replayed: false,
+            details: None,
+        }
+    }
+
+    pub fn wallet(mut self, wallet_id: impl Into<String>) -> Self {
+        self.wallet_id = Some(wallet_id.into());
+        self
+    }
+
+    pub fn message(mut self, message: &[u8]) -> Self {
+        self.message_sha256 = Some(hex::encode(Sha256::digest(message)));
+        self
+    }
+
+    pub fn shares(mut self, share_ids: Vec<String>) -> Self {
+        self.share_ids = share_ids;
+        self
+    }
+
+    pub fn replayed(mut self, replayed: bool) -> Self {
+        self.replayed = replayed;
+        self
+    }
+
+    pub fn details(mut self, details: Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+}
+
+/// Result of checking the hash chain.
+#[derive(Debug, Serialize)]
+pub struct AuditVerification {
+    pub valid: bool,
+    pub entries: u64,
+    /// Hash of the last entry; record it elsewhere to detect a rewritten log.
+    pub head_hash: String,
+    pub error: Option<String>,
+}
+
+struct ChainHead {
+    file: File,
+    next_seq: u64,
+    last_hash: String,
+}
+
+/// Append-only, hash-chained JSON lines log of key and signing operations.
+/// Edits, reordering and removal of entries break the chain; a log cut short
+/// while the server runs no longer ends at the head it appended last.
+pub struct AuditLog {
+    path: PathBuf,
+    head: Mutex<ChainHead>,
+}
+
+impl AuditLog {
+    /// Opens the log at `path`, refusing a log whose chain does not verify.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).with_context(|| format!("creating {}", parent.display()))?;
+        }
+
+        let (next_seq, last_hash) = if path.exists() {
+            verify_chain(&path)?
+        } else {
+            (0, GENESIS_HASH.to_string())
+        };
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("opening audit log {}", path.display()))?;
+        log::info!("Opened audit log {} with {next_seq} entries, head {last_hash}", path.display());
+
+        Ok(Self {
+            path,
+            head: Mutex::new(ChainHead { file, next_seq, last_hash }),
+        })
+    }
+
+    /// Appends `event` with the outcome of `result` and passes `result` on.
+    /// A successful operation that cannot be recorded fails instead; failures
+    /// are returned unchanged even if recording them fails.
+    pub fn record<T>(&self, event: AuditEvent, result: Result<T, MpcError>) -> Result<T, MpcError> {
+        let (outcome, error) = match &result {
+            Ok(_) if event.replayed => (AuditOutcome::Replayed, None),
+            Ok(_) => (AuditOutcome::Success, None),
+            Err(e) => (AuditOutcome::Failure, Some(format!("{}: {e}", e.code()))),
+        };
+
+        if let Err(e) = self.append(event, outcome, error) {
+            log::error!("Failed to write audit log: {e:#}");
+            return result.and(Err(MpcError::InternalError("Failed to write audit log".to_string())));
+        }
+        result
+    }
+
+    /// Records `event` as refused with `error`, for callers denied before the
+    /// operation runs, and passes `error` on.
+    pub fn deny(&self, event: AuditEvent, error: MpcError) -> MpcError {
+        match self.record::<()>(event, Err(error)) {
+            Err(error) => error,
+            Ok(()) => unreachable!("a failure is recorded as a failure"),
+        }
+    }
+
+    fn append(&self, event: AuditEvent, outcome: AuditOutcome, error: Option<String>) -> Result<()> {
+        let mut head = self.head.lock().unwrap();
+        let record = AuditRecord {
+            seq: head.next_seq,
+            timestamp: Utc::now(),
+            action: event.action,
+            caller: event.caller,
+            wallet_id: event.wallet_id,
+            message_sha256: event.message_sha256,
+            share_ids: event.share_ids,
+            outcome,
+            error,
+            details: event.details,
+            prev_hash: head.last_hash.clone(),
+        };
+        let entry = AuditEntry {
+            hash: record.hash(),
+            record,
+        };
+
+        let mut line = serde_json::to_vec(&entry)?;
+        line.push(b'\n');
+        head.file.write_all(&line)?;
+        head.file.sync_data()?;
+
+        head.next_seq += 1;
+        head.last_hash = entry.hash;
+        Ok(())
+    }
+
+    /// Checks every entry's hash and link, and that the log still ends at
+    /// the last entry this server appended.
+    pub fn verify(&self) -> AuditVerification {
+        let head = self.head.lock().unwrap();
+        let (valid, error) = match verify_chain(&self.path) {
+            Ok((entries, hash)) if entries == head.next_seq && hash == head.last_hash => (true, None),
+            Ok((entries, _)) => (
+                false,
+                Some(format!("log ends after {entries} entries, but {} were written", head.next_seq)),
+            ),
+            Err(e) => (false, Some(format!("{e:#}"))),
+        };
+
+        AuditVerification {
+            valid,
+            entries: head.next_seq,
+            head_hash: head.last_hash.clone(),
+            error,
+        }
+    }
+
+    /// The raw log, one JSON entry per line.
+    pub fn export(&self) -> Result<Vec<u8>> {
+        let _head = self.head.lock().unwrap();
+        fs::read(&self.path).with_context(|| format!("reading audit log {}", self.path.display()))
+    }
+}
+
+/// Walks the chain and returns the entry count and the hash of the last entry.
+fn verify_chain(path: &Path) -> Result<(u64, String)> {
+    let file = File::open(path).with_context(|| format!("opening audit log {}", path.display()))?;
+    let mut next_seq = 0u64;
+    let mut last_hash = GENESIS_HASH.to_string();
+
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        let entry: AuditEntry = serde_json::from_str(&line)
+            .with_context(|| format!("audit log line {} is not a valid entry", index + 1))?;
+        let record = &entry.record;
+
+        if record.seq != next_seq {
+            return Err(anyhow!("entry {} found where entry {next_seq} was expected", record.seq));
+        }
+        if record.prev_hash != last_hash {
+            return Err(anyhow!("entry {} does not link to the previous entry", record.seq));
+        }
+        if record.hash() != entry.hash {
+            return Err(anyhow!("entry {} has been modified", record.seq));
+        }
+
+        next_seq += 1;
+        last_hash = entry.hash;
+    }
+    Ok((next_seq, last_hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sign_event(wallet_id: &str) -> AuditEvent {
+        AuditEvent::new(AuditAction::Sign, "payroll-service")
+            .wallet(wallet_id)
+            .message(b"payroll batch")
+            .shares(vec!["share_1".to_string(), "share_2".to_string()])
+            .details(serde_json::json!({ "idempotency_key": "run-1" }))
+    }
+
+    #[test]
+    fn chains_entries_and_resumes_after_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.log");
+
+        let audit = AuditLog::open(&path).unwrap();
+        audit.record(sign_event("w1"), Ok(())).unwrap();
+        let failure: Result<(), _> = Err(MpcError::PolicyViolation("program not allowed".to_string()));
+        assert!(audit.record(sign_event("w1"), failure).is_err());
+        drop(audit);
+
+        let audit = AuditLog::open(&path).unwrap();
+        audit.record(AuditEvent::new(AuditAction::WalletRemoved, "expiry-sweeper").wallet("w1"), Ok(())).unwrap();
+        let verification = audit.verify();
+        assert!(verification.valid, "{:?}", verification.error);
+        assert_eq!(verification.entries, 3);
+
+        let export = String::from_utf8(audit.export().unwrap()).unwrap();
+        let entries: Vec<AuditEntry> = export.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(entries[1].record.outcome, AuditOutcome::Failure);
+        assert!(entries[1].record.error.as_deref().unwrap().starts_with("POLICY_VIOLATION"));
+        assert_eq!(entries[2].record.prev_hash, entries[1].hash);
+        assert_eq!(entries[2].record.action, AuditAction::WalletRemoved);
+        assert_eq!(verification.head_hash, entries[2].hash);
+    }
+
+    #[test]
+    fn detects_edits_and_truncation() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.log");
+        let audit = AuditLog::open(&path).unwrap();
+        for wallet_id in ["w1", "w2", "w3"] {
+            audit.record(sign_event(wallet_id), Ok(())).unwrap();
+        }
+        let original = fs::read_to_string(&path).unwrap();
+
+        fs::write(&path, original.replacen("\"w2\"", "\"w9\"", 1)).unwrap();
+        assert!(!audit.verify().valid);
+        assert!(AuditLog::open(&path).is_err());
+
+        let without_last: String = original.lines().take(2).map(|line| format!("{line}\n")).collect();
+        fs::write(&path, without_last).unwrap();
+        assert!(!audit.verify().valid);
+
+        let without_first: String = original.lines().skip(1).map(|line| format!("{line}\n")).collect();
+        fs::write(&path, without_first).unwrap();
+        assert!(AuditLog::open(&path).is_err());
+    }
+}
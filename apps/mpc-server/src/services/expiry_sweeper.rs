@@ -0,0 +1,151 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::errors::MpcError;
+use crate::services::audit_log::{AuditAction, AuditEvent, AuditLog};
+use crate::services::key_management::{CleanupStats, KeyManager};
+
+/// Caller identity of sweeper entries in the audit log.
+const SWEEPER_CALLER: &str = "system:expiry-sweeper";
+
+/// Counters exported on `/metrics` for the key share expiry sweeper.
+#[derive(Default)]
+pub struct SweeperMetrics {
+    runs: AtomicU64,
+    failures: AtomicU64,
+    shares_removed: AtomicU64,
+    wallets_removed: AtomicU64,
+    wallets_remaining: AtomicU64,
+}
+
+impl SweeperMetrics {
+    fn record(&self, stats: &CleanupStats) {
+        self.runs.fetch_add(1, Ordering::Relaxed);
+        self.shares_removed.fetch_add(stats.shares_removed as u64, Ordering::Relaxed);
+        self.wallets_removed.fetch_add(stats.wallets_removed as u64, Ordering::Relaxed);
+        self.wallets_remaining.store(stats.wallets_remaining as u64, Ordering::Relaxed);
+    }
+
+    fn record_failure(&self) {
+        self.runs.fetch_add(1, Ordering::Relaxed);
+        self.failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Prometheus text exposition of the counters.
+    pub fn render(&self) -> String {
+        let metrics = [
+            ("mpc_share_sweeps_total", "counter", "Expiry sweeps run", &self.runs),
+            ("mpc_share_sweep_failures_total", "counter", "Expiry sweeps that failed", &self.failures),
+            ("mpc_expired_shares_removed_total", "counter", "Expired key shares removed", &self.shares_removed),
+            ("mpc_expired_wallets_removed_total", "counter", "Wallets archived after all shares expired", &self.wallets_removed),
+            ("mpc_wallets", "gauge", "Wallets remaining after the last sweep", &self.wallets_remaining),
+        ];
+
+        metrics
+            .iter()
+            .map(|(name, kind, help, value)| {
+                format!(
+                    "# HELP {name} {help}\n# TYPE {name} {kind}\n{name} {}\n",
+                    value.load(Ordering::Relaxed)
+                )
+            })
+            .collect()
+    }
+}
+
+/// Runs [`KeyManager::cleanup_expired_keys`] every `interval` for the lifetime
+/// of the server.
+pub fn spawn(key_manager: KeyManager, interval: Duration, metrics: Arc<SweeperMetrics>, audit: Arc<AuditLog>) {
+    actix_rt::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            sweep(&key_manager, &metrics, &audit).await;
+        }
+    });
+}
+
+async fn sweep(key_manager: &KeyManager, metrics: &SweeperMetrics, audit: &AuditLog) {
+    match key_manager.cleanup_expired_keys().await {
+        Ok(stats) => {
+            metrics.record(&stats);
+            for removed in &stats.removed {
+                let action = if removed.wallet_removed { AuditAction::WalletRemoved } else { AuditAction::ShareRemoved };
+                let event = AuditEvent::new(action, SWEEPER_CALLER)
+                    .wallet(removed.wallet_id.clone())
+                    .shares(removed.share_ids.clone())
+                    .details(serde_json::json!({ "archived": true }));
+                // Write failures are logged by the audit log itself
+                let _ = audit.record(event, Ok::<_, MpcError>(()));
+            }
+            log::info!(
+                target: "mpc_server::expiry_sweeper",
+                "event=share_sweep shares_removed={} wallets_removed={} wallets_remaining={}",
+                stats.shares_removed,
+                stats.wallets_removed,
+                stats.wallets_remaining
+            );
+        }
+        Err(e) => {
+            metrics.record_failure();
+            log::error!(target: "mpc_server::expiry_sweeper", "event=share_sweep_failed error=\"{e:#}\"");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::KeyShare;
+    use crate::services::audit_log::{AuditEntry, AuditOutcome};
+    use crate::services::key_management::tests::{manager, wallet};
+    use crate::services::key_store::WalletRecord;
+
+    #[tokio::test]
+    async fn sweep_archives_expired_shares_and_audits_them() {
+        let dir = tempfile::tempdir().unwrap();
+        let key_manager = manager(dir.path(), [7; 32]).unwrap();
+        let expired = KeyShare::new("wallet-1".to_string(), vec![1; 34], Some(chrono::Duration::zero()));
+        let live = KeyShare::new("wallet-1".to_string(), vec![2; 34], None);
+        let (expired_id, live_id) = (expired.id.clone(), live.id.clone());
+        key_manager.store_wallet(wallet("wallet-1"), vec![expired, live]).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+
+        let metrics = SweeperMetrics::default();
+        let audit = AuditLog::open(dir.path().join("audit.log")).unwrap();
+        sweep(&key_manager, &metrics, &audit).await;
+
+        // The record is archived as it was before the sweep
+        let archived: Vec<_> = std::fs::read_dir(dir.path().join("archive")).unwrap().collect();
+        assert_eq!(archived.len(), 1);
+        let record: WalletRecord =
+            serde_json::from_slice(&std::fs::read(archived[0].as_ref().unwrap().path()).unwrap()).unwrap();
+        let archived_ids: Vec<_> = record.shares.iter().map(|share| share.id.clone()).collect();
+        assert_eq!(archived_ids, [expired_id.clone(), live_id.clone()]);
+
+        // The live store keeps the wallet with only its live share
+        let record: WalletRecord =
+            serde_json::from_slice(&std::fs::read(dir.path().join("wallet-1.json")).unwrap()).unwrap();
+        assert!(record.wallet.is_some());
+        let stored_ids: Vec<_> = record.shares.iter().map(|share| share.id.clone()).collect();
+        assert_eq!(stored_ids, [live_id]);
+
+        let log = String::from_utf8(audit.export().unwrap()).unwrap();
+        let entries: Vec<AuditEntry> = log.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(entries.len(), 1);
+        let entry = &entries[0].record;
+        assert_eq!(entry.action, AuditAction::ShareRemoved);
+        assert_eq!(entry.caller, SWEEPER_CALLER);
+        assert_eq!(entry.wallet_id.as_deref(), Some("wallet-1"));
+        assert_eq!(entry.share_ids, [expired_id]);
+        assert_eq!(entry.outcome, AuditOutcome::Success);
+        assert_eq!(entry.details, Some(serde_json::json!({ "archived": true })));
+
+        let rendered = metrics.render();
+        assert!(rendered.contains("\nmpc_expired_shares_removed_total 1\n"));
+        assert!(rendered.contains("\nmpc_share_sweeps_total 1\n"));
+        assert!(rendered.contains("\nmpc_expired_wallets_removed_total 0\n"));
+    }
+}
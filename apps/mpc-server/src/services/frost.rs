@@ -0,0 +1,555 @@
+//! FROST(Ed25519, SHA-512) threshold primitives (RFC 9591).
+//!
+//! Key generation follows the Pedersen DKG used by FROST: every participant
+//! deals a random polynomial of degree `threshold - 1`, proves knowledge of its
+//! constant term and sends one Shamir share to each peer. A participant's
+//! signing share is the sum of the shares it received, so no single party
+//! ever holds the group secret.
+//!
+//! Signing is the two-round FROST protocol: signers first publish nonce
+//! commitments, then each returns a signature share bound to the full
+//! commitment list. The coordinator sums the shares into a plain Ed25519
+//! signature over the group public key.
+
+use curve25519_dalek::constants::ED25519_BASEPOINT_POINT;
+use curve25519_dalek::edwards::EdwardsPoint;
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::Identity;
+use rand::{CryptoRng, RngCore};
+use sha2::{Digest, Sha512};
+use std::collections::BTreeMap;
+
+const CONTEXT_STRING: &[u8] = b"FROST-ED25519-SHA512-v1";
+
+/// Non-zero participant index, also the x-coordinate of its Shamir share.
+pub type Identifier = u16;
+
+#[derive(Debug, thiserror::Error)]
+pub enum FrostError {
+    #[error("invalid parameters: {0}")]
+    InvalidParameters(String),
+
+    #[error("invalid proof of knowledge from participant {0}")]
+    InvalidProofOfKnowledge(Identifier),
+
+    #[error("invalid secret share from participant {0}")]
+    InvalidSecretShare(Identifier),
+
+    #[error("insufficient shares: got {got}, need {need}")]
+    InsufficientShares { got: usize, need: usize },
+
+    #[error("participant {0} is not part of the signing package")]
+    UnknownParticipant(Identifier),
+
+    #[error("missing signature share from participant {0}")]
+    MissingSignatureShare(Identifier),
+
+    #[error("invalid signature share from participant {0}")]
+    InvalidSignatureShare(Identifier),
+}
+
+/// Long-lived secret material held by a single participant.
+#[derive(Clone)]
+pub struct KeyPackage {
+    pub identifier: Identifier,
+    pub signing_share: Scalar,
+    pub verifying_share: EdwardsPoint,
+    pub group_public: EdwardsPoint,
+    pub threshold: u16,
+}
+
+/// Public output of the DKG, identical for every participant.
+#[derive(Clone)]
+pub struct PublicKeyPackage {
+    pub verifying_shares: BTreeMap<Identifier, EdwardsPoint>,
+    pub group_public: EdwardsPoint,
+    pub threshold: u16,
+}
+
+impl PublicKeyPackage {
+    /// Group public key in the standard 32-byte Ed25519 encoding.
+    pub fn group_public_bytes(&self) -> [u8; 32] {
+        self.group_public.compress().to_bytes()
+    }
+}
+
+/// Broadcast by every participant in DKG round one.
+struct Round1Package {
+    commitment: Vec<EdwardsPoint>,
+    proof_r: EdwardsPoint,
+    proof_mu: Scalar,
+}
+
+struct SecretPackage {
+    identifier: Identifier,
+    coefficients: Vec<Scalar>,
+}
+
+/// Runs the complete DKG for `total` participants and returns every
+/// participant's key package along with the shared public key package.
+pub fn keygen<R: RngCore + CryptoRng>(
+    threshold: u16,
+    total: u16,
+    rng: &mut R,
+) -> Result<(Vec<KeyPackage>, PublicKeyPackage), FrostError> {
+    if total == 0 || threshold == 0 || threshold > total {
+        return Err(FrostError::InvalidParameters(format!(
+            "threshold {threshold} of {total} participants"
+        )));
+    }
+
+    let identifiers: Vec<Identifier> = (1..=total).collect();
+
+    // Round one: every participant commits to its polynomial and proves
+    // knowledge of the constant term.
+    let mut secrets = Vec::with_capacity(identifiers.len());
+    let mut round1 = BTreeMap::new();
+    for &id in &identifiers {
+        let (secret, package) = dkg_part1(id, threshold, rng);
+        secrets.push(secret);
+        round1.insert(id, package);
+    }
+
+    for (&id, package) in &round1 {
+        verify_proof_of_knowledge(id, package)?;
+    }
+
+    // Round two: each participant evaluates its polynomial at every peer's
+    // identifier and the receiver checks the share against the commitment.
+    let mut received: BTreeMap<Identifier, Scalar> = BTreeMap::new();
+    for secret in &secrets {
+        let sender_commitment = &round1[&secret.identifier].commitment;
+        for &receiver in &identifiers {
+            let share = evaluate_polynomial(&secret.coefficients, receiver);
+            if ED25519_BASEPOINT_POINT * share != evaluate_commitment(sender_commitment, receiver) {
+                return Err(FrostError::InvalidSecretShare(secret.identifier));
+            }
+            *received.entry(receiver).or_insert(Scalar::ZERO) += share;
+        }
+    }
+
+    let group_public = round1
+        .values()
+        .map(|package| package.commitment[0])
+        .fold(EdwardsPoint::identity(), |acc, point| acc + point);
+
+    let key_packages: Vec<KeyPackage> = received
+        .into_iter()
+        .map(|(identifier, signing_share)| KeyPackage {
+            identifier,
+            signing_share,
+            verifying_share: ED25519_BASEPOINT_POINT * signing_share,
+            group_public,
+            threshold,
+        })
+        .collect();
+
+    let public_package = PublicKeyPackage {
+        verifying_shares: key_packages
+            .iter()
+            .map(|kp| (kp.identifier, kp.verifying_share))
+            .collect(),
+        group_public,
+        threshold,
+    };
+
+    Ok((key_packages, public_package))
+}
+
+fn dkg_part1<R: RngCore + CryptoRng>(
+    identifier: Identifier,
+    threshold: u16,
+    rng: &mut R,
+) -> (SecretPackage, Round1Package) {
+    let coefficients: Vec<Scalar> = (0..threshold).map(|_| Scalar::random(rng)).collect();
+    let commitment: Vec<EdwardsPoint> = coefficients
+        .iter()
+        .map(|c| ED25519_BASEPOINT_POINT * c)
+        .collect();
+
+    let k = Scalar::random(rng);
+    let proof_r = ED25519_BASEPOINT_POINT * k;
+    let c = dkg_challenge(identifier, &commitment[0], &proof_r);
+    let proof_mu = k + coefficients[0] * c;
+
+    (
+        SecretPackage { identifier, coefficients },
+        Round1Package { commitment, proof_r, proof_mu },
+    )
+}
+
+fn verify_proof_of_knowledge(identifier: Identifier, package: &Round1Package) -> Result<(), FrostError> {
+    let c = dkg_challenge(identifier, &package.commitment[0], &package.proof_r);
+    if ED25519_BASEPOINT_POINT * package.proof_mu - package.commitment[0] * c != package.proof_r {
+        return Err(FrostError::InvalidProofOfKnowledge(identifier));
+    }
+    Ok(())
+}
+
+fn dkg_challenge(identifier: Identifier, verifying_key: &EdwardsPoint, r: &EdwardsPoint) -> Scalar {
+    hash_to_scalar(&[
+        CONTEXT_STRING,
+        b"dkg",
+        identifier_to_scalar(identifier).as_bytes(),
+        verifying_key.compress().as_bytes(),
+        r.compress().as_bytes(),
+    ])
+}
+
+fn evaluate_polynomial(coefficients: &[Scalar], identifier: Identifier) -> Scalar {
+    let x = identifier_to_scalar(identifier);
+    coefficients
+        .iter()
+        .rev()
+        .fold(Scalar::ZERO, |acc, coefficient| acc * x + coefficient)
+}
+
+fn evaluate_commitment(commitment: &[EdwardsPoint], identifier: Identifier) -> EdwardsPoint {
+    let x = identifier_to_scalar(identifier);
+    commitment
+        .iter()
+        .rev()
+        .fold(EdwardsPoint::identity(), |acc, point| acc * x + point)
+}
+
+/// Secret nonces for a single signing session. Consumed by [`sign`] so they
+/// can never be reused for a second message.
+pub struct SigningNonces {
+    hiding: Scalar,
+    binding: Scalar,
+    commitments: SigningCommitments,
+}
+
+/// Public commitments to a participant's signing nonces.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct SigningCommitments {
+    pub hiding: EdwardsPoint,
+    pub binding: EdwardsPoint,
+}
+
+/// Everything a signer needs for round two: the message and every
+/// participant's round-one commitments.
+#[derive(Clone)]
+pub struct SigningPackage {
+    commitments: BTreeMap<Identifier, SigningCommitments>,
+    message: Vec<u8>,
+}
+
+impl SigningPackage {
+    pub fn new(commitments: BTreeMap<Identifier, SigningCommitments>, message: &[u8]) -> Self {
+        Self {
+            commitments,
+            message: message.to_vec(),
+        }
+    }
+
+    pub fn participants(&self) -> Vec<Identifier> {
+        self.commitments.keys().copied().collect()
+    }
+}
+
+/// Round one: generate hiding and binding nonces and their commitments.
+pub fn commit<R: RngCore + CryptoRng>(key_package: &KeyPackage, rng: &mut R) -> (SigningNonces, SigningCommitments) {
+    let hiding = nonce_generate(&key_package.signing_share, rng);
+    let binding = nonce_generate(&key_package.signing_share, rng);
+    let commitments = SigningCommitments {
+        hiding: ED25519_BASEPOINT_POINT * hiding,
+        binding: ED25519_BASEPOINT_POINT * binding,
+    };
+    (SigningNonces { hiding, binding, commitments }, commitments)
+}
+
+/// Round two: produce this participant's signature share.
+pub fn sign(
+    signing_package: &SigningPackage,
+    nonces: SigningNonces,
+    key_package: &KeyPackage,
+) -> Result<Scalar, FrostError> {
+    let signers = signing_package.commitments.len();
+    if signers < key_package.threshold as usize {
+        return Err(FrostError::InsufficientShares { got: signers, need: key_package.threshold as usize });
+    }
+    match signing_package.commitments.get(&key_package.identifier) {
+        Some(commitments) if *commitments == nonces.commitments => {}
+        Some(_) => return Err(FrostError::InvalidSignatureShare(key_package.identifier)),
+        None => return Err(FrostError::UnknownParticipant(key_package.identifier)),
+    }
+
+    signature_share(signing_package, &nonces, key_package)
+}
+
+/// Checks every share against its signer's verifying share and sums them
+/// into a 64-byte Ed25519 signature.
+pub fn aggregate(
+    signing_package: &SigningPackage,
+    signature_shares: &BTreeMap<Identifier, Scalar>,
+    public_key_package: &PublicKeyPackage,
+) -> Result<[u8; 64], FrostError> {
+    let signers = signing_package.commitments.len();
+    if signers < public_key_package.threshold as usize {
+        return Err(FrostError::InsufficientShares { got: signers, need: public_key_package.threshold as usize });
+    }
+    if let Some(&identifier) = signing_package.commitments.keys().find(|id| !signature_shares.contains_key(id)) {
+        return Err(FrostError::MissingSignatureShare(identifier));
+    }
+
+    for (&identifier, share) in signature_shares {
+        verify_signature_share(signing_package, identifier, share, public_key_package)?;
+    }
+
+    Ok(combine(signing_package, signature_shares, &public_key_package.group_public))
+}
+
+/// Verifies a single participant's signature share so a bad share can be
+/// attributed to the participant that produced it.
+pub fn verify_signature_share(
+    signing_package: &SigningPackage,
+    identifier: Identifier,
+    share: &Scalar,
+    public_key_package: &PublicKeyPackage,
+) -> Result<(), FrostError> {
+    let commitments = signing_package
+        .commitments
+        .get(&identifier)
+        .ok_or(FrostError::UnknownParticipant(identifier))?;
+    let verifying_share = public_key_package
+        .verifying_shares
+        .get(&identifier)
+        .ok_or(FrostError::UnknownParticipant(identifier))?;
+
+    let group_public = &public_key_package.group_public;
+    let binding_factors = binding_factors(signing_package, group_public);
+    let group_commitment = group_commitment(signing_package, &binding_factors);
+    let c = challenge(
+        group_commitment.compress().as_bytes(),
+        group_public.compress().as_bytes(),
+        &signing_package.message,
+    );
+    let lambda = lagrange_coefficient(identifier, &signing_package.participants());
+
+    let commitment_share = commitments.hiding + commitments.binding * binding_factors[&identifier];
+    if ED25519_BASEPOINT_POINT * share != commitment_share + verifying_share * (c * lambda) {
+        return Err(FrostError::InvalidSignatureShare(identifier));
+    }
+    Ok(())
+}
+
+fn signature_share(
+    signing_package: &SigningPackage,
+    nonces: &SigningNonces,
+    key_package: &KeyPackage,
+) -> Result<Scalar, FrostError> {
+    let binding_factors = binding_factors(signing_package, &key_package.group_public);
+    let group_commitment = group_commitment(signing_package, &binding_factors);
+    let c = challenge(
+        group_commitment.compress().as_bytes(),
+        key_package.group_public.compress().as_bytes(),
+        &signing_package.message,
+    );
+    let lambda = lagrange_coefficient(key_package.identifier, &signing_package.participants());
+    let rho = binding_factors
+        .get(&key_package.identifier)
+        .ok_or(FrostError::UnknownParticipant(key_package.identifier))?;
+
+    Ok(nonces.hiding + nonces.binding * rho + lambda * key_package.signing_share * c)
+}
+
+fn combine(
+    signing_package: &SigningPackage,
+    signature_shares: &BTreeMap<Identifier, Scalar>,
+    group_public: &EdwardsPoint,
+) -> [u8; 64] {
+    let binding_factors = binding_factors(signing_package, group_public);
+    let group_commitment = group_commitment(signing_package, &binding_factors);
+    let z: Scalar = signature_shares.values().sum();
+
+    let mut signature = [0u8; 64];
+    signature[..32].copy_from_slice(group_commitment.compress().as_bytes());
+    signature[32..].copy_from_slice(z.as_bytes());
+    signature
+}
+
+fn binding_factors(signing_package: &SigningPackage, group_public: &EdwardsPoint) -> BTreeMap<Identifier, Scalar> {
+    let message_hash = hash(&[CONTEXT_STRING, b"msg", &signing_package.message]);
+
+    let mut encoded_commitments = Vec::with_capacity(signing_package.commitments.len() * 96);
+    for (&identifier, commitments) in &signing_package.commitments {
+        encoded_commitments.extend_from_slice(identifier_to_scalar(identifier).as_bytes());
+        encoded_commitments.extend_from_slice(commitments.hiding.compress().as_bytes());
+        encoded_commitments.extend_from_slice(commitments.binding.compress().as_bytes());
+    }
+    let commitments_hash = hash(&[CONTEXT_STRING, b"com", &encoded_commitments]);
+
+    let group_public = group_public.compress();
+    signing_package
+        .commitments
+        .keys()
+        .map(|&identifier| {
+            let rho = hash_to_scalar(&[
+                CONTEXT_STRING,
+                b"rho",
+                group_public.as_bytes(),
+                &message_hash,
+                &commitments_hash,
+                identifier_to_scalar(identifier).as_bytes(),
+            ]);
+            (identifier, rho)
+        })
+        .collect()
+}
+
+fn group_commitment(signing_package: &SigningPackage, binding_factors: &BTreeMap<Identifier, Scalar>) -> EdwardsPoint {
+    signing_package
+        .commitments
+        .iter()
+        .fold(EdwardsPoint::identity(), |acc, (identifier, commitments)| {
+            acc + commitments.hiding + commitments.binding * binding_factors[identifier]
+        })
+}
+
+/// Lagrange coefficient at zero for `identifier` within `participants`.
+fn lagrange_coefficient(identifier: Identifier, participants: &[Identifier]) -> Scalar {
+    let x_i = identifier_to_scalar(identifier);
+    let mut numerator = Scalar::ONE;
+    let mut denominator = Scalar::ONE;
+    for &other in participants.iter().filter(|&&other| other != identifier) {
+        let x_j = identifier_to_scalar(other);
+        numerator *= x_j;
+        denominator *= x_j - x_i;
+    }
+    numerator * denominator.invert()
+}
+
+fn nonce_generate<R: RngCore + CryptoRng>(secret: &Scalar, rng: &mut R) -> Scalar {
+    let mut random_bytes = [0u8; 32];
+    rng.fill_bytes(&mut random_bytes);
+    hash_to_scalar(&[CONTEXT_STRING, b"nonce", &random_bytes, secret.as_bytes()])
+}
+
+/// Ed25519 challenge `H(R || A || M)`, so aggregated signatures verify as
+/// ordinary Ed25519 signatures.
+fn challenge(r: &[u8; 32], public_key: &[u8; 32], message: &[u8]) -> Scalar {
+    hash_to_scalar(&[r, public_key, message])
+}
+
+fn hash(parts: &[&[u8]]) -> [u8; 64] {
+    let mut hasher = Sha512::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+fn hash_to_scalar(parts: &[&[u8]]) -> Scalar {
+    Scalar::from_bytes_mod_order_wide(&hash(parts))
+}
+
+fn identifier_to_scalar(identifier: Identifier) -> Scalar {
+    Scalar::from(identifier as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+    use rand::rngs::OsRng;
+
+    fn verifying_key(public: &PublicKeyPackage) -> VerifyingKey {
+        VerifyingKey::from_bytes(&public.group_public_bytes()).unwrap()
+    }
+
+    fn round_one(signers: &[&KeyPackage], message: &[u8]) -> (SigningPackage, BTreeMap<Identifier, SigningNonces>) {
+        let mut nonces = BTreeMap::new();
+        let mut commitments = BTreeMap::new();
+        for kp in signers {
+            let (n, c) = commit(kp, &mut OsRng);
+            nonces.insert(kp.identifier, n);
+            commitments.insert(kp.identifier, c);
+        }
+        (SigningPackage::new(commitments, message), nonces)
+    }
+
+    fn sign_checked(signers: &[&KeyPackage], public: &PublicKeyPackage, message: &[u8]) -> Result<Signature, FrostError> {
+        let (package, mut nonces) = round_one(signers, message);
+        let mut shares = BTreeMap::new();
+        for kp in signers {
+            shares.insert(kp.identifier, sign(&package, nonces.remove(&kp.identifier).unwrap(), kp)?);
+        }
+        aggregate(&package, &shares, public).map(|bytes| Signature::from_bytes(&bytes))
+    }
+
+    /// Runs both rounds for `signers` without the threshold checks in
+    /// [`sign`] and [`aggregate`], as a colluding minority would.
+    fn sign_unchecked(signers: &[&KeyPackage], public: &PublicKeyPackage, message: &[u8]) -> Signature {
+        let (package, nonces) = round_one(signers, message);
+        let shares: BTreeMap<Identifier, Scalar> = signers
+            .iter()
+            .map(|kp| (kp.identifier, signature_share(&package, &nonces[&kp.identifier], kp).unwrap()))
+            .collect();
+        Signature::from_bytes(&combine(&package, &shares, &public.group_public))
+    }
+
+    #[test]
+    fn every_threshold_subset_produces_a_valid_ed25519_signature() {
+        let (packages, public) = keygen(2, 3, &mut OsRng).unwrap();
+        let message = b"payroll batch";
+        for pair in [[0, 1], [0, 2], [1, 2]] {
+            let signers = [&packages[pair[0]], &packages[pair[1]]];
+            let signature = sign_checked(&signers, &public, message).unwrap();
+            verifying_key(&public).verify(message, &signature).unwrap();
+        }
+
+        let all: Vec<&KeyPackage> = packages.iter().collect();
+        let signature = sign_checked(&all, &public, message).unwrap();
+        verifying_key(&public).verify(message, &signature).unwrap();
+    }
+
+    #[test]
+    fn subsets_below_threshold_cannot_sign() {
+        let (packages, public) = keygen(3, 5, &mut OsRng).unwrap();
+        let message = b"payroll batch";
+        for subset in [vec![0], vec![1, 3], vec![2, 4], vec![0, 4]] {
+            let signers: Vec<&KeyPackage> = subset.iter().map(|&i| &packages[i]).collect();
+
+            assert!(matches!(
+                sign_checked(&signers, &public, message),
+                Err(FrostError::InsufficientShares { .. })
+            ));
+
+            let forged = sign_unchecked(&signers, &public, message);
+            assert!(verifying_key(&public).verify(message, &forged).is_err());
+        }
+    }
+
+    #[test]
+    fn single_share_is_not_the_group_key() {
+        let (packages, public) = keygen(2, 3, &mut OsRng).unwrap();
+        for kp in &packages {
+            assert_ne!(kp.verifying_share, public.group_public);
+        }
+    }
+
+    #[test]
+    fn tampered_share_is_attributed_to_its_signer() {
+        let (packages, public) = keygen(2, 3, &mut OsRng).unwrap();
+        let signers = [&packages[0], &packages[2]];
+        let (package, mut nonces) = round_one(&signers, b"payroll batch");
+        let mut shares = BTreeMap::new();
+        for kp in signers {
+            shares.insert(kp.identifier, sign(&package, nonces.remove(&kp.identifier).unwrap(), kp).unwrap());
+        }
+        *shares.get_mut(&3).unwrap() += Scalar::ONE;
+
+        assert!(matches!(
+            aggregate(&package, &shares, &public),
+            Err(FrostError::InvalidSignatureShare(3))
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_parameters() {
+        assert!(keygen(0, 3, &mut OsRng).is_err());
+        assert!(keygen(4, 3, &mut OsRng).is_err());
+        assert!(keygen(1, 0, &mut OsRng).is_err());
+    }
+}
@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::OnceCell;
+
+use crate::errors::MpcError;
+
+/// Remembers the result of an operation under a client-chosen key for a
+/// fixed window. Replays with the same parameters get the original result;
+/// concurrent replays wait for the first attempt instead of running twice.
+/// Failed attempts are not remembered.
+pub struct IdempotencyCache<P, R> {
+    window: Duration,
+    entries: Mutex<HashMap<String, Entry<P, R>>>,
+}
+
+struct Entry<P, R> {
+    params: P,
+    created_at: Instant,
+    result: Arc<OnceCell<R>>,
+}
+
+impl<P: PartialEq, R: Clone> IdempotencyCache<P, R> {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    /// Runs `op` unless `key` was already used; returns the result and
+    /// whether it was replayed from the cache. Reusing a key with different
+    /// parameters is an [`MpcError::IdempotencyConflict`].
+    pub async fn run<F, Fut>(&self, key: String, params: P, op: F) -> Result<(R, bool), MpcError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<R, MpcError>>,
+    {
+        let cell = {
+            let key = key.clone();
+            let mut entries = self.entries.lock().unwrap();
+            entries.retain(|_, entry| entry.created_at.elapsed() < self.window);
+
+            match entries.get(&key) {
+                Some(entry) if entry.params != params => return Err(MpcError::IdempotencyConflict(key)),
+                Some(entry) => entry.result.clone(),
+                None => {
+                    let cell = Arc::new(OnceCell::new());
+                    entries.insert(key, Entry { params, created_at: Instant::now(), result: cell.clone() });
+                    cell
+                }
+            }
+        };
+
+        let mut ran = false;
+        let result = cell
+            .get_or_try_init(|| {
+                ran = true;
+                op()
+            })
+            .await;
+        match result {
+            Ok(result) => Ok((result.clone(), !ran)),
+            Err(e) => {
+                // Forget the failed attempt so the key can be retried with any parameters
+                let mut entries = self.entries.lock().unwrap();
+                if entries.get(&key).is_some_and(|entry| Arc::ptr_eq(&entry.result, &cell) && !cell.initialized()) {
+                    entries.remove(&key);
+                }
+                Err(e)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn replays_results_and_rejects_changed_parameters() {
+        let cache = IdempotencyCache::new(Duration::from_secs(60));
+        let calls = AtomicUsize::new(0);
+        let op = || async { Ok(calls.fetch_add(1, Ordering::SeqCst)) };
+
+        assert_eq!(cache.run("req-1".to_string(), (2, 3), op).await.unwrap(), (0, false));
+        assert_eq!(cache.run("req-1".to_string(), (2, 3), op).await.unwrap(), (0, true));
+        assert!(matches!(
+            cache.run("req-1".to_string(), (2, 5), op).await,
+            Err(MpcError::IdempotencyConflict(_))
+        ));
+        assert_eq!(cache.run("req-2".to_string(), (2, 3), op).await.unwrap(), (1, false));
+
+        // Failures are not cached
+        let failing = || async { Err::<usize, _>(MpcError::InternalError("boom".to_string())) };
+        assert!(cache.run("req-3".to_string(), (2, 3), failing).await.is_err());
+        assert_eq!(cache.run("req-3".to_string(), (2, 3), op).await.unwrap(), (2, false));
+    }
+
+    #[tokio::test]
+    async fn failed_attempts_can_be_retried_with_other_parameters() {
+        let cache = IdempotencyCache::new(Duration::from_secs(60));
+        let rejected = || async { Err::<usize, _>(MpcError::InvalidThreshold("threshold 0".to_string())) };
+        assert!(matches!(
+            cache.run("req-1".to_string(), (0, 3), rejected).await,
+            Err(MpcError::InvalidThreshold(_))
+        ));
+
+        let op = || async { Ok(7) };
+        assert_eq!(cache.run("req-1".to_string(), (2, 3), op).await.unwrap(), (7, false));
+        assert!(matches!(
+            cache.run("req-1".to_string(), (0, 3), op).await,
+            Err(MpcError::IdempotencyConflict(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn forgets_keys_after_the_window() {
+        let cache = IdempotencyCache::new(Duration::ZERO);
+        let calls = AtomicUsize::new(0);
+        let op = || async { Ok(calls.fetch_add(1, Ordering::SeqCst)) };
+
+        cache.run("req-1".to_string(), (), op).await.unwrap();
+        assert_eq!(cache.run("req-1".to_string(), (), op).await.unwrap(), (1, false));
+    }
+}
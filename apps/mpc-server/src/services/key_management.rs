@@ -1,100 +1,361 @@
 use std::collections::HashMap;
 use std::sync::Arc;
+use anyhow::Result;
 use tokio::sync::RwLock;
 use crate::models::{KeyShare, WalletInfo};
+use crate::services::key_store::{KeyStore, WalletRecord};
+use crate::services::share_cipher::ShareCipher;
 
-#[allow(dead_code)]
-#[derive(Debug, Clone)]
+/// Outcome of one [`KeyManager::cleanup_expired_keys`] sweep.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CleanupStats {
+    pub shares_removed: usize,
+    /// Wallets left without shares; their records are archived, not deleted.
+    pub wallets_removed: usize,
+    pub wallets_remaining: usize,
+    pub removed: Vec<RemovedShares>,
+}
+
+/// Expired shares dropped from one wallet by a sweep.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemovedShares {
+    pub wallet_id: String,
+    pub share_ids: Vec<String>,
+    /// The wallet had no shares left and was archived as well.
+    pub wallet_removed: bool,
+}
+
+/// Wallet and key share registry. Shares are sealed with the master key both
+/// in memory and in the backing [`KeyStore`], and only opened on read.
+#[derive(Clone)]
 pub struct KeyManager {
-    shares: Arc<RwLock<HashMap<String, Vec<KeyShare>>>>, // wallet_id -> shares
+    shares: Arc<RwLock<HashMap<String, Vec<KeyShare>>>>, // wallet_id -> sealed shares
     wallets: Arc<RwLock<HashMap<String, WalletInfo>>>, // wallet_id -> wallet_info
+    store: Arc<dyn KeyStore>,
+    cipher: Arc<ShareCipher>,
 }
 
 impl KeyManager {
-    #[allow(dead_code)]
-    pub fn new() -> Self {
-        Self {
-            shares: Arc::new(RwLock::new(HashMap::new())),
-            wallets: Arc::new(RwLock::new(HashMap::new())),
+    /// Restores every wallet persisted in `store`.
+    pub fn new(store: Arc<dyn KeyStore>, cipher: ShareCipher) -> Result<Self> {
+        let mut shares = HashMap::new();
+        let mut wallets = HashMap::new();
+        for (wallet_id, record) in store.load_all()? {
+            if let Some(wallet) = record.wallet {
+                wallets.insert(wallet_id.clone(), wallet);
+            }
+            if !record.shares.is_empty() {
+                shares.insert(wallet_id, record.shares);
+            }
         }
+        log::info!("Loaded {} wallets from key store", wallets.len());
+
+        Ok(Self {
+            shares: Arc::new(RwLock::new(shares)),
+            wallets: Arc::new(RwLock::new(wallets)),
+            store,
+            cipher: Arc::new(cipher),
+        })
     }
-    
-    #[allow(dead_code)]
-    pub async fn store_wallet(&self, wallet_info: WalletInfo) {
+
+    /// Stores a wallet together with its shares in a single key store write,
+    /// so a wallet is never persisted without its shares.
+    pub async fn store_wallet(&self, wallet_info: WalletInfo, shares: Vec<KeyShare>) -> Result<()> {
+        let sealed = shares
+            .into_iter()
+            .map(|mut share| {
+                share.share_data = self.cipher.seal(&share.share_data, &associated_data(&share))?;
+                Ok(share)
+            })
+            .collect::<Result<Vec<KeyShare>>>()?;
+
+        let mut shares_map = self.shares.write().await;
         let mut wallets = self.wallets.write().await;
-        wallets.insert(wallet_info.wallet_id.clone(), wallet_info);
+        let wallet_id = wallet_info.wallet_id.clone();
+        let record = WalletRecord {
+            wallet: Some(wallet_info.clone()),
+            shares: sealed.clone(),
+        };
+        self.store.save(&wallet_id, &record)?;
+        wallets.insert(wallet_id.clone(), wallet_info);
+        shares_map.insert(wallet_id, sealed);
+        Ok(())
     }
-    
-    #[allow(dead_code)]
+
     pub async fn get_wallet(&self, wallet_id: &str) -> Option<WalletInfo> {
         let wallets = self.wallets.read().await;
         wallets.get(wallet_id).cloned()
     }
-    
-    #[allow(dead_code)]
-    pub async fn store_shares(&self, wallet_id: String, shares: Vec<KeyShare>) {
-        let mut shares_map = self.shares.write().await;
-        shares_map.insert(wallet_id, shares);
+
+    /// Wallet whose `keygen_request` is `keygen_request`. A wallet without
+    /// shares does not count as created.
+    pub async fn wallet_by_keygen_request(&self, keygen_request: &str) -> Option<WalletInfo> {
+        let shares_map = self.shares.read().await;
+        let wallets = self.wallets.read().await;
+        wallets
+            .values()
+            .find(|wallet| {
+                wallet.keygen_request.as_deref() == Some(keygen_request)
+                    && shares_map.get(&wallet.wallet_id).is_some_and(|shares| !shares.is_empty())
+            })
+            .cloned()
     }
-    
-    #[allow(dead_code)]
-    pub async fn get_shares(&self, wallet_id: &str) -> Option<Vec<KeyShare>> {
+
+    /// Ids of a wallet's shares in the order they were stored, without
+    /// opening any of them.
+    pub async fn share_ids(&self, wallet_id: &str) -> Option<Vec<String>> {
         let shares_map = self.shares.read().await;
-        shares_map.get(wallet_id).cloned()
+        shares_map
+            .get(wallet_id)
+            .map(|shares| shares.iter().map(|share| share.id.clone()).collect())
     }
-    
-    #[allow(dead_code)]
-    pub async fn get_shares_by_ids(&self, wallet_id: &str, share_ids: &[String]) -> Option<Vec<KeyShare>> {
+
+    pub async fn get_shares_by_ids(&self, wallet_id: &str, share_ids: &[String]) -> Result<Option<Vec<KeyShare>>> {
         let shares_map = self.shares.read().await;
         if let Some(all_shares) = shares_map.get(wallet_id) {
             let filtered_shares: Vec<KeyShare> = all_shares
                 .iter()
                 .filter(|share| share_ids.contains(&share.id))
-                .cloned()
-                .collect();
-            
+                .map(|share| self.open(share))
+                .collect::<Result<_>>()?;
+
             if filtered_shares.len() == share_ids.len() {
-                Some(filtered_shares)
+                Ok(Some(filtered_shares))
             } else {
-                None
+                Ok(None)
             }
         } else {
-            None
+            Ok(None)
         }
     }
-    
-    #[allow(dead_code)]
-    pub async fn cleanup_expired_keys(&self) {
+
+    /// Drops expired shares, and wallets left without any, from memory and
+    /// from the live store. Each affected record is archived first.
+    pub async fn cleanup_expired_keys(&self) -> Result<CleanupStats> {
         let mut shares_map = self.shares.write().await;
         let mut wallets_map = self.wallets.write().await;
-        
+        let mut stats = CleanupStats::default();
+
         // Remove expired shares
-        for (_wallet_id, shares) in shares_map.iter_mut() {
-            shares.retain(|share| !share.is_expired());
+        let mut changed_wallets = Vec::new();
+        for (wallet_id, shares) in shares_map.iter_mut() {
+            if !shares.iter().any(KeyShare::is_expired) {
+                continue;
+            }
+            let record = WalletRecord {
+                wallet: wallets_map.get(wallet_id).cloned(),
+                shares: shares.clone(),
+            };
+            self.store.archive(wallet_id, &record)?;
+
+            let (expired, live): (Vec<KeyShare>, Vec<KeyShare>) =
+                std::mem::take(shares).into_iter().partition(KeyShare::is_expired);
+            *shares = live;
+            stats.shares_removed += expired.len();
+            let share_ids = expired.into_iter().map(|share| share.id).collect();
+            stats.removed.push(RemovedShares {
+                wallet_id: wallet_id.clone(),
+                share_ids,
+                wallet_removed: shares.is_empty(),
+            });
+            changed_wallets.push(wallet_id.clone());
         }
-        
-        // Remove wallets with no shares
-        let expired_wallets: Vec<String> = shares_map
+
+        // Remove wallets left with no shares
+        let expired_wallets: Vec<String> = changed_wallets
             .iter()
-            .filter(|(_, shares)| shares.is_empty())
-            .map(|(wallet_id, _)| wallet_id.clone())
+            .filter(|wallet_id| shares_map.get(*wallet_id).is_some_and(Vec::is_empty))
+            .cloned()
             .collect();
-        
-        for wallet_id in expired_wallets {
-            shares_map.remove(&wallet_id);
-            wallets_map.remove(&wallet_id);
+
+        for wallet_id in &expired_wallets {
+            shares_map.remove(wallet_id);
+            wallets_map.remove(wallet_id);
+            self.store.remove(wallet_id)?;
         }
-        
-        log::info!("Cleaned up expired keys. Remaining wallets: {}", wallets_map.len());
+        stats.wallets_removed = expired_wallets.len();
+
+        for wallet_id in changed_wallets.iter().filter(|id| !expired_wallets.contains(id)) {
+            self.persist(wallet_id, &shares_map, &wallets_map)?;
+        }
+
+        stats.wallets_remaining = wallets_map.len();
+        Ok(stats)
     }
-    
-    #[allow(dead_code)]
-    pub async fn remove_wallet(&self, wallet_id: &str) {
+
+    /// Deletes a wallet and its shares, in memory and in the backing store.
+    /// Returns the ids of the removed shares, or `None` for an unknown wallet.
+    pub async fn remove_wallet(&self, wallet_id: &str) -> Result<Option<Vec<String>>> {
         let mut shares_map = self.shares.write().await;
         let mut wallets_map = self.wallets.write().await;
-        
-        shares_map.remove(wallet_id);
+        if !wallets_map.contains_key(wallet_id) && !shares_map.contains_key(wallet_id) {
+            return Ok(None);
+        }
+
+        self.store.remove(wallet_id)?;
         wallets_map.remove(wallet_id);
+        let shares = shares_map.remove(wallet_id).unwrap_or_default();
+        Ok(Some(shares.into_iter().map(|share| share.id).collect()))
+    }
+
+    fn open(&self, sealed: &KeyShare) -> Result<KeyShare> {
+        let mut share = sealed.clone();
+        share.share_data = self.cipher.open(&sealed.share_data, &associated_data(sealed))?;
+        Ok(share)
     }
+
+    fn persist(
+        &self,
+        wallet_id: &str,
+        shares_map: &HashMap<String, Vec<KeyShare>>,
+        wallets_map: &HashMap<String, WalletInfo>,
+    ) -> Result<()> {
+        let record = WalletRecord {
+            wallet: wallets_map.get(wallet_id).cloned(),
+            shares: shares_map.get(wallet_id).cloned().unwrap_or_default(),
+        };
+        self.store.save(wallet_id, &record)
+    }
+}
+
+fn associated_data(share: &KeyShare) -> Vec<u8> {
+    format!("{}:{}", share.wallet_id, share.id).into_bytes()
 }
 
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::services::key_store::FileKeyStore;
+    use chrono::Utc;
+
+    pub(crate) fn wallet(wallet_id: &str) -> WalletInfo {
+        WalletInfo {
+            wallet_id: wallet_id.to_string(),
+            public_key: "pk".to_string(),
+            verifying_shares: Default::default(),
+            threshold: 2,
+            total_shares: 3,
+            organization_id: None,
+            keygen_request: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    pub(crate) fn manager(dir: &std::path::Path, master_key: [u8; 32]) -> Result<KeyManager> {
+        KeyManager::new(Arc::new(FileKeyStore::new(dir)?), ShareCipher::new(master_key))
+    }
+
+    #[tokio::test]
+    async fn shares_survive_restart_and_are_sealed_on_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let shares: Vec<KeyShare> = (0..3)
+            .map(|i| KeyShare::new("wallet-1".to_string(), vec![0xAB; 34].into_iter().chain([i]).collect(), None))
+            .collect();
+        let ids: Vec<String> = shares.iter().map(|s| s.id.clone()).collect();
+
+        {
+            let manager = manager(dir.path(), [7; 32]).unwrap();
+            manager.store_wallet(wallet("wallet-1"), shares.clone()).await.unwrap();
+        }
+
+        let on_disk = std::fs::read(dir.path().join("wallet-1.json")).unwrap();
+        let record: WalletRecord = serde_json::from_slice(&on_disk).unwrap();
+        assert!(record.shares.iter().all(|s| !s.share_data.windows(8).any(|w| w == [0xAB; 8])));
+
+        let restarted = manager(dir.path(), [7; 32]).unwrap();
+        assert!(restarted.get_wallet("wallet-1").await.is_some());
+        let restored = restarted
+            .get_shares_by_ids("wallet-1", &ids[..2])
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(restored.len(), 2);
+        assert_eq!(restored[0].share_data, shares[0].share_data);
+        assert!(restarted
+            .get_shares_by_ids("wallet-1", &[ids[0].clone(), "missing".to_string()])
+            .await
+            .unwrap()
+            .is_none());
+
+        let wrong_key = manager(dir.path(), [8; 32]).unwrap();
+        assert!(wrong_key.get_shares_by_ids("wallet-1", &ids).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn keygen_request_ids_survive_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let share = || vec![KeyShare::new("wallet-1".to_string(), vec![1; 34], None)];
+        {
+            let manager = manager(dir.path(), [7; 32]).unwrap();
+            let mut info = wallet("wallet-1");
+            info.keygen_request = Some("org-1:req-1".to_string());
+            manager.store_wallet(info, share()).await.unwrap();
+            manager.store_wallet(wallet("wallet-2"), share()).await.unwrap();
+        }
+        // A wallet record left without shares, e.g. by a crash mid-keygen
+        let mut orphan = wallet("wallet-3");
+        orphan.keygen_request = Some("org-1:req-2".to_string());
+        let record = WalletRecord { wallet: Some(orphan), shares: Vec::new() };
+        FileKeyStore::new(dir.path()).unwrap().save("wallet-3", &record).unwrap();
+
+        let restarted = manager(dir.path(), [7; 32]).unwrap();
+        let found = restarted.wallet_by_keygen_request("org-1:req-1").await.unwrap();
+        assert_eq!(found.wallet_id, "wallet-1");
+        assert!(restarted.wallet_by_keygen_request("org-2:req-1").await.is_none());
+        // The retry creates the wallet instead of replaying one without shares
+        assert!(restarted.get_wallet("wallet-3").await.is_some());
+        assert!(restarted.wallet_by_keygen_request("org-1:req-2").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn cleanup_removes_expired_wallets_from_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let key_manager = manager(dir.path(), [7; 32]).unwrap();
+        for (wallet_id, retention) in [("expired", Some(chrono::Duration::zero())), ("live", None)] {
+            let shares = (0..3)
+                .map(|_| KeyShare::new(wallet_id.to_string(), vec![1; 34], retention))
+                .collect();
+            key_manager.store_wallet(wallet(wallet_id), shares).await.unwrap();
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+
+        let stats = key_manager.cleanup_expired_keys().await.unwrap();
+        assert_eq!(
+            (stats.shares_removed, stats.wallets_removed, stats.wallets_remaining),
+            (3, 1, 1)
+        );
+        assert_eq!(stats.removed.len(), 1);
+        assert_eq!(stats.removed[0].wallet_id, "expired");
+        assert_eq!(stats.removed[0].share_ids.len(), 3);
+        assert!(stats.removed[0].wallet_removed);
+        assert!(key_manager.get_wallet("expired").await.is_none());
+        assert!(!dir.path().join("expired.json").exists());
+
+        // The record is archived with its sealed shares, not deleted
+        let archived: Vec<_> = std::fs::read_dir(dir.path().join("archive")).unwrap().collect();
+        assert_eq!(archived.len(), 1);
+        let path = archived[0].as_ref().unwrap().path();
+        assert!(path.file_name().unwrap().to_str().unwrap().starts_with("expired."));
+        let record: WalletRecord = serde_json::from_slice(&std::fs::read(path).unwrap()).unwrap();
+        assert_eq!(record.shares.len(), 3);
+        assert!(record.wallet.is_some());
+
+        let restarted = manager(dir.path(), [7; 32]).unwrap();
+        assert!(restarted.get_wallet("live").await.is_some());
+        assert!(restarted.get_wallet("expired").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn remove_wallet_deletes_the_persisted_record() {
+        let dir = tempfile::tempdir().unwrap();
+        let key_manager = manager(dir.path(), [7; 32]).unwrap();
+        let shares: Vec<KeyShare> = (0..2).map(|_| KeyShare::new("wallet-1".to_string(), vec![1; 34], None)).collect();
+        let ids: Vec<String> = shares.iter().map(|s| s.id.clone()).collect();
+        key_manager.store_wallet(wallet("wallet-1"), shares).await.unwrap();
+
+        assert_eq!(key_manager.remove_wallet("wallet-1").await.unwrap(), Some(ids));
+        assert_eq!(key_manager.remove_wallet("wallet-1").await.unwrap(), None);
+        assert!(key_manager.get_wallet("wallet-1").await.is_none());
+        assert!(!dir.path().join("wallet-1.json").exists());
+    }
+}
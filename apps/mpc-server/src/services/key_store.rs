@@ -0,0 +1,122 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::models::{KeyShare, WalletInfo};
+use crate::services::share_cipher::write_private;
+
+/// Everything persisted for one wallet. `shares[*].share_data` is always
+/// sealed by the [`ShareCipher`](crate::services::share_cipher::ShareCipher).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WalletRecord {
+    pub wallet: Option<WalletInfo>,
+    pub shares: Vec<KeyShare>,
+}
+
+/// Storage backend for [`KeyManager`](crate::services::key_management::KeyManager).
+pub trait KeyStore: Send + Sync {
+    fn save(&self, wallet_id: &str, record: &WalletRecord) -> Result<()>;
+    fn remove(&self, wallet_id: &str) -> Result<()>;
+    /// Keeps a copy of `record` outside the live set, for recovery by an
+    /// operator; archived records are never loaded.
+    fn archive(&self, wallet_id: &str, record: &WalletRecord) -> Result<()>;
+    fn load_all(&self) -> Result<Vec<(String, WalletRecord)>>;
+}
+
+/// One JSON file per wallet, replaced atomically on every write. Archived
+/// records go to `archive/<wallet_id>.<unix time>.json`.
+pub struct FileKeyStore {
+    dir: PathBuf,
+}
+
+impl FileKeyStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("creating key store directory {}", dir.display()))?;
+        Ok(Self { dir })
+    }
+
+    fn path(&self, wallet_id: &str) -> PathBuf {
+        self.dir.join(format!("{wallet_id}.json"))
+    }
+}
+
+impl KeyStore for FileKeyStore {
+    fn save(&self, wallet_id: &str, record: &WalletRecord) -> Result<()> {
+        let path = self.path(wallet_id);
+        let tmp = path.with_extension("json.tmp");
+        write_private(&tmp, &serde_json::to_vec(record)?)?;
+        fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    fn remove(&self, wallet_id: &str) -> Result<()> {
+        match fs::remove_file(self.path(wallet_id)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn archive(&self, wallet_id: &str, record: &WalletRecord) -> Result<()> {
+        let dir = self.dir.join("archive");
+        fs::create_dir_all(&dir).with_context(|| format!("creating archive directory {}", dir.display()))?;
+        let path = dir.join(format!("{wallet_id}.{}.json", chrono::Utc::now().timestamp_millis()));
+        write_private(&path, &serde_json::to_vec(record)?)?;
+        Ok(())
+    }
+
+    fn load_all(&self) -> Result<Vec<(String, WalletRecord)>> {
+        let mut records = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            let Some(wallet_id) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            let record: WalletRecord = serde_json::from_slice(&fs::read(&path)?)
+                .with_context(|| format!("parsing wallet record {}", path.display()))?;
+            records.push((wallet_id.to_string(), record));
+        }
+        Ok(records)
+    }
+}
+
+/// Non-persistent backend for tests.
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryKeyStore {
+    records: std::sync::Mutex<std::collections::HashMap<String, WalletRecord>>,
+    archived: std::sync::Mutex<Vec<(String, WalletRecord)>>,
+}
+
+#[cfg(test)]
+impl KeyStore for MemoryKeyStore {
+    fn save(&self, wallet_id: &str, record: &WalletRecord) -> Result<()> {
+        self.records.lock().unwrap().insert(wallet_id.to_string(), record.clone());
+        Ok(())
+    }
+
+    fn remove(&self, wallet_id: &str) -> Result<()> {
+        self.records.lock().unwrap().remove(wallet_id);
+        Ok(())
+    }
+
+    fn archive(&self, wallet_id: &str, record: &WalletRecord) -> Result<()> {
+        self.archived.lock().unwrap().push((wallet_id.to_string(), record.clone()));
+        Ok(())
+    }
+
+    fn load_all(&self) -> Result<Vec<(String, WalletRecord)>> {
+        Ok(self
+            .records
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(wallet_id, record)| (wallet_id.clone(), record.clone()))
+            .collect())
+    }
+}
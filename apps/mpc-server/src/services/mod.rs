@@ -1,2 +1,13 @@
+pub mod audit_log;
+pub mod frost;
 pub mod mpc_engine;
-pub mod key_management;
\ No newline at end of file
+pub mod mpc_service;
+pub mod key_management;
+pub mod key_store;
+pub mod expiry_sweeper;
+pub mod idempotency;
+pub mod payroll_program;
+pub mod policy;
+pub mod share_cipher;
+pub mod solana_rpc;
+pub mod solana_tx;
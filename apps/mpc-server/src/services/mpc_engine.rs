@@ -1,13 +1,25 @@
-use ed25519_dalek::{SigningKey, VerifyingKey, Signature, Signer};
+use curve25519_dalek::constants::ED25519_BASEPOINT_POINT;
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint};
+use curve25519_dalek::scalar::Scalar;
+use ed25519_dalek::{VerifyingKey, Signature, Verifier};
+use base64::{engine::general_purpose, Engine as _};
 use rand::rngs::OsRng;
 use uuid::Uuid;
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use crate::errors::MpcError;
+use crate::models::{self, WalletInfo};
+use crate::services::idempotency::IdempotencyCache;
+use crate::services::frost::{self, Identifier, KeyPackage, PublicKeyPackage, SigningCommitments, SigningNonces, SigningPackage};
+use crate::services::key_management::KeyManager;
+use crate::services::policy::{PolicyEngine, Spend};
 
 #[derive(Clone)]
 pub struct KeyShare {
     pub share_id: String,
-    pub secret_bytes: Vec<u8>,
+    pub key_package: KeyPackage,
 }
 
 #[derive(Clone)]
@@ -18,69 +30,539 @@ pub struct WalletKey {
     pub threshold: usize,
 }
 
+/// Bounds on the `threshold` / `total_shares` accepted by keygen.
+#[derive(Debug, Clone, Copy)]
+pub struct KeygenLimits {
+    pub min_threshold: usize,
+    pub max_total_shares: usize,
+}
+
+impl Default for KeygenLimits {
+    fn default() -> Self {
+        Self {
+            min_threshold: 1,
+            max_total_shares: 16,
+        }
+    }
+}
+
+impl KeygenLimits {
+    pub fn validate(&self, threshold: usize, total_shares: usize) -> Result<(), MpcError> {
+        let invalid = |reason: String| Err(MpcError::InvalidThreshold(reason));
+        if total_shares == 0 {
+            return invalid("total_shares must be at least 1".to_string());
+        }
+        if total_shares > self.max_total_shares {
+            return invalid(format!("total_shares must be at most {}", self.max_total_shares));
+        }
+        if threshold < self.min_threshold.max(1) {
+            return invalid(format!("threshold must be at least {}", self.min_threshold.max(1)));
+        }
+        if threshold > total_shares {
+            return invalid(format!("threshold {threshold} exceeds total_shares {total_shares}"));
+        }
+        Ok(())
+    }
+}
+
+/// Threshold key generation and signing. Wallets and their shares live in the
+/// [`KeyManager`]; shares are only opened for the duration of an operation.
 pub struct MpcEngine {
-    wallets: Arc<Mutex<HashMap<String, WalletKey>>>,
+    key_manager: KeyManager,
+    /// How long new shares are kept; `None` keeps them until removed.
+    share_retention: Option<chrono::Duration>,
+    keygen_limits: KeygenLimits,
+    policies: PolicyEngine,
+    /// Signatures by wallet and idempotency key, parameterised by the
+    /// SHA-256 of the signed message.
+    sign_cache: IdempotencyCache<[u8; 32], Signature>,
 }
 
 impl MpcEngine {
-    pub fn new() -> Self {
+    pub fn new(
+        key_manager: KeyManager,
+        share_retention: Option<chrono::Duration>,
+        keygen_limits: KeygenLimits,
+        policies: PolicyEngine,
+        sign_idempotency_window: Duration,
+    ) -> Self {
         Self {
-            wallets: Arc::new(Mutex::new(HashMap::new())),
+            key_manager,
+            share_retention,
+            keygen_limits,
+            policies,
+            sign_cache: IdempotencyCache::new(sign_idempotency_window),
         }
     }
 
-    pub fn generate_key(&self, threshold: usize, total_shares: usize) -> Result<WalletKey, String> {
-        // Simplified threshold key generation for V0
-        // In production, use proper MPC protocols like FROST or GG20
-        
-        let signing_key = SigningKey::generate(&mut OsRng);
-        let verifying_key = signing_key.verifying_key();
+    pub async fn generate_key(
+        &self,
+        threshold: usize,
+        total_shares: usize,
+        organization_id: Option<String>,
+        keygen_request: Option<String>,
+    ) -> Result<WalletKey, MpcError> {
+        self.keygen_limits.validate(threshold, total_shares)?;
+        let threshold_u16 = u16::try_from(threshold)
+            .map_err(|_| MpcError::InvalidThreshold("threshold too large".to_string()))?;
+        let total_u16 = u16::try_from(total_shares)
+            .map_err(|_| MpcError::InvalidThreshold("total_shares too large".to_string()))?;
+
+        // FROST distributed key generation: each share is a Shamir share of
+        // the group secret. The DKG never computes the secret itself, but all
+        // n participants run in this process, so the server holds every share.
+        let (key_packages, public_key_package) =
+            frost::keygen(threshold_u16, total_u16, &mut OsRng)?;
+
+        let public_key = VerifyingKey::from_bytes(&public_key_package.group_public_bytes())
+            .map_err(|e| MpcError::InternalError(format!("Invalid group public key: {e}")))?;
         let wallet_id = Uuid::new_v4().to_string();
-        
-        // Simulate threshold shares (simplified for PoC)
-        let shares: Vec<KeyShare> = (0..total_shares)
-            .map(|i| KeyShare {
-                share_id: format!("share_{wallet_id}_{i}"),
-                secret_bytes: signing_key.to_bytes().to_vec(), // In production: split key properly
+
+        let shares: Vec<KeyShare> = key_packages
+            .into_iter()
+            .map(|key_package| KeyShare {
+                share_id: format!("share_{wallet_id}_{}", key_package.identifier),
+                key_package,
             })
             .collect();
 
-        let wallet_key = WalletKey {
+        let wallet_info = WalletInfo {
             wallet_id: wallet_id.clone(),
-            public_key: verifying_key,
+            public_key: general_purpose::STANDARD.encode(public_key.as_bytes()),
+            verifying_shares: public_key_package
+                .verifying_shares
+                .iter()
+                .map(|(&id, point)| (id, general_purpose::STANDARD.encode(point.compress().as_bytes())))
+                .collect(),
+            threshold: threshold as u32,
+            total_shares: total_shares as u32,
+            organization_id,
+            keygen_request,
+            created_at: chrono::Utc::now(),
+        };
+        let stored_shares = shares
+            .iter()
+            .map(|share| {
+                let mut stored = models::KeyShare::new(
+                    wallet_id.clone(),
+                    encode_share(&share.key_package),
+                    self.share_retention,
+                );
+                stored.id = share.share_id.clone();
+                stored
+            })
+            .collect();
+
+        self.key_manager.store_wallet(wallet_info, stored_shares).await?;
+
+        Ok(WalletKey {
+            wallet_id,
+            public_key,
             shares,
             threshold,
-        };
+        })
+    }
+
+    /// Coordinates a two-round FROST signing session among the given shares
+    /// and returns the aggregated Ed25519 signature. The message must satisfy
+    /// the wallet's signing policy.
+    pub async fn sign_message(
+        &self,
+        wallet_id: &str,
+        message: &[u8],
+        share_ids: Vec<String>,
+    ) -> Result<Signature, MpcError> {
+        let spend = self.policies.authorize(wallet_id, message).await?;
+        let result = self.run_signing(wallet_id, message, share_ids).await;
+        if let (Err(_), Some(spend)) = (&result, spend) {
+            self.policies.refund(spend);
+        }
+        result
+    }
+
+    /// Checks a message against the wallet's signing policy ahead of a
+    /// step-wise signing session. Its transfers count against the daily cap
+    /// until the returned spend is passed to [`Self::refund`].
+    pub async fn authorize_message(&self, wallet_id: &str, message: &[u8]) -> Result<Option<Spend>, MpcError> {
+        self.policies.authorize(wallet_id, message).await
+    }
 
-        self.wallets.lock().unwrap().insert(wallet_id, wallet_key.clone());
-        Ok(wallet_key)
+    /// Returns the spend of a message that was authorized but not signed.
+    pub fn refund(&self, spend: Spend) {
+        self.policies.refund(spend);
     }
 
-    pub fn sign_message(
+    /// Decoded `payroll_solana` instructions of a message, for audit records.
+    pub fn describe_message(&self, message: &[u8]) -> Vec<serde_json::Value> {
+        self.policies.describe(message)
+    }
+
+    async fn run_signing(
         &self,
         wallet_id: &str,
         message: &[u8],
         share_ids: Vec<String>,
-    ) -> Result<Signature, String> {
-        let wallets = self.wallets.lock().unwrap();
-        let wallet = wallets.get(wallet_id).ok_or("Wallet not found")?;
+    ) -> Result<Signature, MpcError> {
+        let (wallet, public_key, public_key_package) = self.load_public(wallet_id).await?;
+        let threshold = wallet.threshold as usize;
 
-        if share_ids.len() < wallet.threshold {
-            return Err("Insufficient shares for threshold".to_string());
+        if share_ids.len() < threshold {
+            return Err(MpcError::InsufficientShares { provided: share_ids.len(), threshold });
         }
 
-        // Verify share IDs exist
-        let valid_shares: Vec<&KeyShare> = wallet.shares.iter()
-            .filter(|s| share_ids.contains(&s.share_id))
+        // Only the selected shares that belong to the wallet are opened
+        let selected: Vec<String> = self.stored_share_ids(&wallet).await?
+            .into_iter()
+            .filter(|id| share_ids.contains(id))
             .collect();
+        if selected.len() < threshold {
+            return Err(MpcError::InsufficientShares { provided: selected.len(), threshold });
+        }
+
+        let shares = self.load_shares(&wallet, &selected).await?;
+        let valid_shares: Vec<&KeyPackage> = shares.iter().map(|s| &s.key_package).collect();
+
+        // Round one: every selected signer commits to fresh nonces
+        let mut nonces = BTreeMap::new();
+        let mut commitments = BTreeMap::new();
+        for key_package in &valid_shares {
+            let (signer_nonces, signer_commitments) = frost::commit(key_package, &mut OsRng);
+            nonces.insert(key_package.identifier, signer_nonces);
+            commitments.insert(key_package.identifier, signer_commitments);
+        }
+        let signing_package = SigningPackage::new(commitments, message);
+
+        // Round two: each signer returns its share over the full commitment list
+        let mut signature_shares = BTreeMap::new();
+        for key_package in &valid_shares {
+            let signer_nonces = nonces
+                .remove(&key_package.identifier)
+                .ok_or_else(|| MpcError::SignatureError("Missing signing nonces".to_string()))?;
+            let share = frost::sign(&signing_package, signer_nonces, key_package)?;
+            signature_shares.insert(key_package.identifier, share);
+        }
+
+        let signature = Signature::from_bytes(
+            &frost::aggregate(&signing_package, &signature_shares, &public_key_package)?,
+        );
+
+        public_key
+            .verify(message, &signature)
+            .map_err(|e| MpcError::SignatureError(format!("Signature verification failed: {e}")))?;
+
+        Ok(signature)
+    }
+
+    /// [`sign_message`](Self::sign_message) deduplicated by `idempotency_key`:
+    /// a retry for the same wallet and message returns the original signature
+    /// without running another signing session. Returns whether the
+    /// signature was replayed.
+    pub async fn sign_message_idempotent(
+        &self,
+        wallet_id: &str,
+        message: &[u8],
+        share_ids: Vec<String>,
+        idempotency_key: &str,
+    ) -> Result<(Signature, bool), MpcError> {
+        let message_hash: [u8; 32] = Sha256::digest(message).into();
+        self.sign_cache
+            .run(format!("{wallet_id}:{idempotency_key}"), message_hash, || {
+                self.sign_message(wallet_id, message, share_ids)
+            })
+            .await
+    }
+
+    /// Wallet created by the keygen request `keygen_request`, if any.
+    pub async fn wallet_for_keygen_request(&self, keygen_request: &str) -> Option<WalletInfo> {
+        self.key_manager.wallet_by_keygen_request(keygen_request).await
+    }
+
+    /// Organization that owns a wallet.
+    pub async fn wallet_organization(&self, wallet_id: &str) -> Result<Option<String>, MpcError> {
+        let wallet = self.wallet(wallet_id).await?;
+        Ok(wallet.organization_id)
+    }
+
+    /// Public key material of a wallet, for verifying signature shares.
+    pub async fn public_keys(&self, wallet_id: &str) -> Result<(VerifyingKey, PublicKeyPackage), MpcError> {
+        let (_, public_key, public_key_package) = self.load_public(wallet_id).await?;
+        Ok((public_key, public_key_package))
+    }
+
+    /// Share ids of a wallet, in participant order. No share is opened.
+    pub async fn share_ids(&self, wallet_id: &str) -> Result<Vec<String>, MpcError> {
+        let wallet = self.wallet(wallet_id).await?;
+        self.stored_share_ids(&wallet).await
+    }
 
-        if valid_shares.len() < wallet.threshold {
-            return Err("Invalid share IDs provided".to_string());
+    /// Round one of a step-wise signing session: commit to fresh nonces for
+    /// each listed share.
+    pub async fn commit(
+        &self,
+        wallet_id: &str,
+        share_ids: &[String],
+    ) -> Result<Vec<(Identifier, SigningNonces, SigningCommitments)>, MpcError> {
+        let (wallet, _, _) = self.load_public(wallet_id).await?;
+        let shares = self.load_shares(&wallet, share_ids).await?;
+
+        share_ids
+            .iter()
+            .map(|share_id| {
+                let share = shares.iter()
+                    .find(|s| &s.share_id == share_id)
+                    .ok_or_else(|| MpcError::InvalidRequest(format!("Unknown share ID: {share_id}")))?;
+                let (nonces, commitments) = frost::commit(&share.key_package, &mut OsRng);
+                Ok((share.key_package.identifier, nonces, commitments))
+            })
+            .collect()
+    }
+
+    /// Round two of a step-wise signing session for a single participant.
+    /// Only that participant's share is opened.
+    pub async fn sign_share(
+        &self,
+        wallet_id: &str,
+        share_id: &str,
+        identifier: Identifier,
+        signing_package: &SigningPackage,
+        nonces: SigningNonces,
+    ) -> Result<Scalar, MpcError> {
+        let wallet = self.wallet(wallet_id).await?;
+        let shares = self.load_shares(&wallet, &[share_id.to_string()]).await?;
+        let share = shares.iter()
+            .find(|s| s.key_package.identifier == identifier)
+            .ok_or_else(|| MpcError::InvalidRequest(format!("Unknown participant: {identifier}")))?;
+
+        Ok(frost::sign(signing_package, nonces, &share.key_package)?)
+    }
+
+    /// Permanently deletes a wallet and its shares; funds held by its key
+    /// can no longer be moved. Returns the ids of the deleted shares.
+    pub async fn remove_wallet(&self, wallet_id: &str) -> Result<Vec<String>, MpcError> {
+        self.key_manager
+            .remove_wallet(wallet_id)
+            .await?
+            .ok_or_else(|| MpcError::KeyNotFound(wallet_id.to_string()))
+    }
+
+    async fn wallet(&self, wallet_id: &str) -> Result<WalletInfo, MpcError> {
+        self.key_manager
+            .get_wallet(wallet_id)
+            .await
+            .ok_or_else(|| MpcError::KeyNotFound(wallet_id.to_string()))
+    }
+
+    async fn load_public(&self, wallet_id: &str) -> Result<(WalletInfo, VerifyingKey, PublicKeyPackage), MpcError> {
+        let wallet = self.wallet(wallet_id).await?;
+
+        let public_key_bytes = decode_point(&wallet.public_key)?;
+        let public_key = VerifyingKey::from_bytes(&public_key_bytes)
+            .map_err(|e| MpcError::InternalError(format!("Invalid wallet public key: {e}")))?;
+
+        let verifying_shares = wallet
+            .verifying_shares
+            .iter()
+            .map(|(&id, encoded)| Ok((id, decompress(&decode_point(encoded)?)?)))
+            .collect::<Result<BTreeMap<Identifier, EdwardsPoint>, MpcError>>()?;
+
+        let public_key_package = PublicKeyPackage {
+            verifying_shares,
+            group_public: decompress(&public_key_bytes)?,
+            threshold: wallet.threshold as u16,
+        };
+        Ok((wallet, public_key, public_key_package))
+    }
+
+    /// Ids of the wallet's stored shares, without opening them.
+    async fn stored_share_ids(&self, wallet: &WalletInfo) -> Result<Vec<String>, MpcError> {
+        self.key_manager
+            .share_ids(&wallet.wallet_id)
+            .await
+            .ok_or_else(|| MpcError::KeyNotFound(wallet.wallet_id.clone()))
+    }
+
+    /// Opens the listed shares of the wallet from the key manager.
+    async fn load_shares(&self, wallet: &WalletInfo, share_ids: &[String]) -> Result<Vec<KeyShare>, MpcError> {
+        let stored = self.key_manager
+            .get_shares_by_ids(&wallet.wallet_id, share_ids)
+            .await?
+            .ok_or_else(|| MpcError::InvalidRequest(format!("Unknown share IDs for wallet {}", wallet.wallet_id)))?;
+
+        let group_public = decompress(&decode_point(&wallet.public_key)?)?;
+        stored
+            .into_iter()
+            .map(|share| {
+                // Refuse expired shares even if the sweeper has not run yet
+                if share.is_expired() {
+                    return Err(MpcError::ShareExpired(share.id));
+                }
+                Ok(KeyShare {
+                    key_package: decode_share(&share.share_data, group_public, wallet.threshold as u16)?,
+                    share_id: share.id,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Serialized signing share: identifier (u16 LE) followed by the scalar.
+fn encode_share(key_package: &KeyPackage) -> Vec<u8> {
+    let mut bytes = key_package.identifier.to_le_bytes().to_vec();
+    bytes.extend_from_slice(key_package.signing_share.as_bytes());
+    bytes
+}
+
+fn decode_share(bytes: &[u8], group_public: EdwardsPoint, threshold: u16) -> Result<KeyPackage, MpcError> {
+    let invalid = |reason: &str| MpcError::InternalError(format!("Invalid key share {reason}"));
+    let (identifier, scalar) = bytes.split_at_checked(2).ok_or_else(|| invalid("length"))?;
+    let identifier = u16::from_le_bytes([identifier[0], identifier[1]]);
+    let scalar_bytes: [u8; 32] = scalar.try_into().map_err(|_| invalid("length"))?;
+    let signing_share = Option::<Scalar>::from(Scalar::from_canonical_bytes(scalar_bytes))
+        .ok_or_else(|| invalid("scalar"))?;
+
+    Ok(KeyPackage {
+        identifier,
+        signing_share,
+        verifying_share: ED25519_BASEPOINT_POINT * signing_share,
+        group_public,
+        threshold,
+    })
+}
+
+fn decode_point(encoded: &str) -> Result<[u8; 32], MpcError> {
+    general_purpose::STANDARD
+        .decode(encoded)
+        .map_err(|e| MpcError::InternalError(format!("Invalid point encoding: {e}")))?
+        .try_into()
+        .map_err(|_| MpcError::InternalError("Invalid point length".to_string()))
+}
+
+fn decompress(bytes: &[u8; 32]) -> Result<EdwardsPoint, MpcError> {
+    CompressedEdwardsY(*bytes)
+        .decompress()
+        .ok_or_else(|| MpcError::InternalError("Invalid curve point".to_string()))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::services::key_store::MemoryKeyStore;
+    use crate::services::share_cipher::ShareCipher;
+    use std::sync::Arc;
+
+    pub(crate) fn engine() -> MpcEngine {
+        engine_with_retention(None)
+    }
+
+    fn engine_with_retention(share_retention: Option<chrono::Duration>) -> MpcEngine {
+        let key_manager = KeyManager::new(Arc::new(MemoryKeyStore::default()), ShareCipher::new([1; 32])).unwrap();
+        MpcEngine::new(key_manager, share_retention, KeygenLimits::default(), PolicyEngine::default(), Duration::from_secs(3600))
+    }
+
+    pub(crate) fn engine_with_policies(policies: PolicyEngine) -> MpcEngine {
+        let key_manager = KeyManager::new(Arc::new(MemoryKeyStore::default()), ShareCipher::new([1; 32])).unwrap();
+        MpcEngine::new(key_manager, None, KeygenLimits::default(), policies, Duration::from_secs(3600))
+    }
+
+    #[tokio::test]
+    async fn sign_message_produces_signature_for_group_key() {
+        let engine = engine();
+        let wallet = engine.generate_key(2, 3, None, None).await.unwrap();
+        let share_ids = vec![wallet.shares[0].share_id.clone(), wallet.shares[2].share_id.clone()];
+
+        let signature = engine.sign_message(&wallet.wallet_id, b"transfer", share_ids).await.unwrap();
+        assert!(wallet.public_key.verify_strict(b"transfer", &signature).is_ok());
+    }
+
+    #[tokio::test]
+    async fn sign_message_rejects_shares_below_threshold() {
+        let engine = engine();
+        let wallet = engine.generate_key(3, 5, None, None).await.unwrap();
+        let share_ids: Vec<String> = wallet.shares[..2].iter().map(|s| s.share_id.clone()).collect();
+
+        assert!(matches!(
+            engine.sign_message(&wallet.wallet_id, b"transfer", share_ids.clone()).await,
+            Err(MpcError::InsufficientShares { provided: 2, threshold: 3 })
+        ));
+        assert!(matches!(
+            engine.sign_message("missing-wallet", b"transfer", share_ids).await,
+            Err(MpcError::KeyNotFound(_))
+        ));
+
+        // Padding with unknown ids must not count towards the threshold
+        let padded = vec![
+            wallet.shares[0].share_id.clone(),
+            wallet.shares[1].share_id.clone(),
+            "share_unknown".to_string(),
+        ];
+        assert!(matches!(
+            engine.sign_message(&wallet.wallet_id, b"transfer", padded).await,
+            Err(MpcError::InsufficientShares { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn wallets_are_served_from_the_key_manager() {
+        let key_manager = KeyManager::new(Arc::new(MemoryKeyStore::default()), ShareCipher::new([1; 32])).unwrap();
+        let wallet = MpcEngine::new(key_manager.clone(), None, KeygenLimits::default(), PolicyEngine::default(), Duration::from_secs(3600)).generate_key(2, 3, None, None).await.unwrap();
+
+        // A second engine over the same key manager sees the same wallet
+        let engine = MpcEngine::new(key_manager, None, KeygenLimits::default(), PolicyEngine::default(), Duration::from_secs(3600));
+        let share_ids = engine.share_ids(&wallet.wallet_id).await.unwrap();
+        let signature = engine.sign_message(&wallet.wallet_id, b"transfer", share_ids).await.unwrap();
+        assert!(wallet.public_key.verify_strict(b"transfer", &signature).is_ok());
+    }
+
+    #[tokio::test]
+    async fn sign_message_refuses_expired_shares() {
+        let engine = engine_with_retention(Some(chrono::Duration::zero()));
+        let wallet = engine.generate_key(2, 3, None, None).await.unwrap();
+        let share_ids: Vec<String> = wallet.shares.iter().map(|s| s.share_id.clone()).collect();
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+
+        let err = engine.sign_message(&wallet.wallet_id, b"transfer", share_ids).await.unwrap_err();
+        assert!(matches!(err, MpcError::ShareExpired(_)), "{err}");
+    }
+
+    #[tokio::test]
+    async fn generate_key_rejects_invalid_parameters() {
+        let engine = engine();
+        for (threshold, total_shares) in [(0, 3), (4, 3), (0, 0), (1, 17)] {
+            assert!(
+                matches!(engine.generate_key(threshold, total_shares, None, None).await, Err(MpcError::InvalidThreshold(_))),
+                "{threshold}-of-{total_shares}"
+            );
         }
 
-        // Reconstruct signing key (simplified for V0)
-        let secret = SigningKey::from_bytes(&valid_shares[0].secret_bytes.clone().try_into().map_err(|_| "Invalid secret key length")?);
+        let strict = KeygenLimits { min_threshold: 2, max_total_shares: 5 };
+        assert!(strict.validate(1, 3).is_err());
+        assert!(strict.validate(2, 6).is_err());
+        assert!(strict.validate(2, 5).is_ok());
+    }
+
+    #[tokio::test]
+    async fn idempotent_signing_replays_the_original_signature() {
+        let engine = engine();
+        let wallet = engine.generate_key(2, 3, None, None).await.unwrap();
+        let share_ids: Vec<String> = wallet.shares[..2].iter().map(|s| s.share_id.clone()).collect();
 
-        Ok(secret.sign(message))
+        let (first, replayed) = engine
+            .sign_message_idempotent(&wallet.wallet_id, b"payroll-42", share_ids.clone(), "run-42")
+            .await
+            .unwrap();
+        assert!(!replayed);
+
+        // FROST nonces are fresh per session, so only a replay yields identical bytes
+        let (second, replayed) = engine
+            .sign_message_idempotent(&wallet.wallet_id, b"payroll-42", share_ids.clone(), "run-42")
+            .await
+            .unwrap();
+        assert!(replayed);
+        assert_eq!(first, second);
+
+        assert!(matches!(
+            engine.sign_message_idempotent(&wallet.wallet_id, b"payroll-43", share_ids, "run-42").await,
+            Err(MpcError::IdempotencyConflict(_))
+        ));
     }
-}
\ No newline at end of file
+}
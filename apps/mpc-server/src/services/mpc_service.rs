@@ -1,212 +1,385 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
 use uuid::Uuid;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use solana_sdk::pubkey::Pubkey;
-use solana_client::rpc_client::RpcClient;
-use solana_sdk::signature::Signature;
 use anyhow::Result;
+use base64::{engine::general_purpose, Engine as _};
+use curve25519_dalek::scalar::Scalar;
+use ed25519_dalek::{Signature, SigningKey, Verifier};
+use sha2::{Digest, Sha256};
 
+use crate::errors::MpcError;
 use crate::models::*;
+use crate::services::frost::{self, FrostError, Identifier, SigningPackage};
+use crate::services::mpc_engine::MpcEngine;
+use crate::services::solana_rpc::SolanaRpc;
 
+/// Squads v4 PDA seeds: the multisig is `[prefix, "multisig", create_key]`
+/// and its vaults are `[prefix, multisig, "vault", index]`.
+const SQUADS_SEED_PREFIX: &[u8] = b"multisig";
+const SQUADS_SEED_MULTISIG: &[u8] = b"multisig";
+const SQUADS_SEED_VAULT: &[u8] = b"vault";
+const SQUADS_VAULT_INDEX: u8 = 0;
+
+/// Domain of the hash that derives a Squads `create_key` from a member set.
+const CREATE_KEY_DOMAIN: &[u8] = b"payrollx:squads-create-key:v1";
+
+/// What a signing session was opened for.
+pub struct SessionInfo {
+    pub key_id: String,
+    pub message: Vec<u8>,
+    /// Share id by participant id.
+    pub participants: BTreeMap<u32, String>,
+}
+
+/// Step-wise signing sessions on top of the wallets held by [`MpcEngine`].
 #[derive(Clone)]
 pub struct MpcService {
-    keys: Arc<RwLock<HashMap<Uuid, MpcKey>>>,
+    engine: Arc<MpcEngine>,
     signatures: Arc<RwLock<HashMap<Uuid, MpcSignature>>>,
-    rpc_client: RpcClient,
+    /// How long a session and its nonces are kept after step one.
+    session_ttl: chrono::Duration,
+    rpc_client: SolanaRpc,
+    multisig_program_id: Pubkey,
 }
 
 impl MpcService {
-    pub fn new() -> Self {
-        let rpc_url = std::env::var("SOLANA_RPC_URL")
-            .unwrap_or_else(|_| "https://api.devnet.solana.com".to_string());
-        
+    pub fn new(engine: Arc<MpcEngine>, rpc_url: String, multisig_program_id: Pubkey, session_ttl: Duration) -> Self {
         Self {
-            keys: Arc::new(RwLock::new(HashMap::new())),
+            engine,
             signatures: Arc::new(RwLock::new(HashMap::new())),
-            rpc_client: RpcClient::new(rpc_url),
-        }
-    }
-
-    pub async fn generate_keys(&self, request: KeygenRequest) -> Result<KeygenResponse, Box<dyn std::error::Error>> {
-        let key_id = Uuid::new_v4();
-        
-        // Generate individual key pairs for each participant
-        let mut participant_keys = Vec::new();
-        
-        for i in 0..request.participant_count {
-            let keypair = solana_sdk::signer::keypair::Keypair::new();
-            let public_key = keypair.pubkey().to_string();
-            let private_key = bs58::encode(keypair.to_bytes()).into_string();
-            
-            participant_keys.push(ParticipantKey {
-                participant_id: i,
-                public_key: public_key.clone(),
-                private_key_share: private_key, // In production, this should be encrypted
-            });
+            session_ttl: chrono::Duration::from_std(session_ttl).unwrap_or(chrono::Duration::MAX),
+            rpc_client: SolanaRpc::new(rpc_url),
+            multisig_program_id,
         }
-        
-        // Store the key set
-        let mpc_key = MpcKey {
-            id: key_id,
-            organization_id: request.organization_id,
-            public_key: Vec::new(), // Will be set after aggregation
-            threshold: request.participant_count,
-            total_participants: request.participant_count,
-            created_at: Utc::now(),
-        };
-        
-        {
-            let mut keys = self.keys.write().await;
-            keys.insert(key_id, mpc_key);
+    }
+
+    fn is_expired(&self, created_at: DateTime<Utc>) -> bool {
+        created_at.checked_add_signed(self.session_ttl).is_some_and(|expires_at| Utc::now() > expires_at)
+    }
+
+    /// Ends a session that produced no signature, returning its spend to
+    /// the wallet's daily cap.
+    fn discard(&self, session: MpcSignature) {
+        if let Some(spend) = session.spend {
+            self.engine.refund(spend);
         }
-        
-        Ok(KeygenResponse {
-            key_id: key_id.to_string(),
-            aggregated_public_key: "".to_string(), // Will be set after aggregation
-            participant_keys,
-            created_at: Utc::now().to_rfc3339(),
-        })
     }
 
-    pub async fn aggregate_keys(&self, request: AggregateKeysRequest) -> Result<AggregateKeysResponse, Box<dyn std::error::Error>> {
-        // Parse public keys
-        let pubkeys: Result<Vec<Pubkey>, _> = request.participant_keys
+    /// The live session `signature_id`. An expired session is discarded,
+    /// along with its nonces, and reported as not found.
+    fn live_session<'a>(
+        &self,
+        signatures: &'a mut HashMap<Uuid, MpcSignature>,
+        signature_id: &Uuid,
+    ) -> Result<&'a mut MpcSignature, MpcError> {
+        if signatures.get(signature_id).is_some_and(|signature| self.is_expired(signature.created_at)) {
+            if let Some(session) = signatures.remove(signature_id) {
+                self.discard(session);
+            }
+        }
+        signatures
+            .get_mut(signature_id)
+            .ok_or_else(|| MpcError::SessionNotFound(signature_id.to_string()))
+    }
+
+    /// Organization that owns a wallet, for authorizing signing requests.
+    pub async fn wallet_organization(&self, key_id: &str) -> Result<Option<String>, MpcError> {
+        self.engine.wallet_organization(key_id).await
+    }
+
+    /// Decoded `payroll_solana` instructions of a session message, for audit
+    /// records.
+    pub fn describe_message(&self, message: &[u8]) -> Vec<serde_json::Value> {
+        self.engine.describe_message(message)
+    }
+
+    /// Wallet, message and roster of a signing session.
+    pub async fn session(&self, signature_id: &str) -> Result<SessionInfo, MpcError> {
+        let signature_id = Uuid::parse_str(signature_id)
+            .map_err(|e| MpcError::InvalidRequest(format!("Invalid signature_id: {e}")))?;
+        self.signatures
+            .read()
+            .await
+            .get(&signature_id)
+            .filter(|signature| !self.is_expired(signature.created_at))
+            .map(|signature| SessionInfo {
+                key_id: signature.key_id.to_string(),
+                message: signature.message.clone(),
+                participants: signature
+                    .participants
+                    .iter()
+                    .map(|(&identifier, share_id)| (identifier as u32, share_id.clone()))
+                    .collect(),
+            })
+            .ok_or_else(|| MpcError::SessionNotFound(signature_id.to_string()))
+    }
+
+    /// Derives the Squads v4 multisig of a participant set: the multisig PDA
+    /// of `create_key` and its default vault. Squads seeds the multisig with
+    /// `create_key` alone, so `create_key` is derived from the salt, the
+    /// sorted members and the threshold; another member set or threshold
+    /// gives another address. Members are returned sorted, so the result does
+    /// not depend on request ordering.
+    pub async fn aggregate_keys(&self, request: AggregateKeysRequest) -> Result<AggregateKeysResponse, MpcError> {
+        if request.participant_keys.is_empty() {
+            return Err(MpcError::EmptyKeySet);
+        }
+
+        let mut pubkeys = request.participant_keys
             .iter()
-            .map(|key_str| key_str.parse::<Pubkey>())
-            .collect();
-        
-        let pubkeys = pubkeys?;
-        
-        // For n-of-n multisig, we need all participants to sign
-        // The aggregated address is typically a Program Derived Address (PDA)
-        // For simplicity, we'll use the first public key as the aggregated address
-        // In a real implementation, you would derive a proper multisig address
-        let aggregated_address = pubkeys[0].to_string();
-        
+            .map(|key_str| parse_key(key_str))
+            .collect::<Result<Vec<Pubkey>, _>>()?;
+
+        pubkeys.sort();
+        if let Some(duplicate) = pubkeys.windows(2).find(|pair| pair[0] == pair[1]) {
+            return Err(MpcError::DuplicateKey(duplicate[0].to_string()));
+        }
+
+        let threshold = request.threshold.unwrap_or(pubkeys.len() as u16);
+        if threshold == 0 || threshold as usize > pubkeys.len() {
+            return Err(MpcError::InvalidThreshold(format!(
+                "threshold {threshold} of {} members",
+                pubkeys.len()
+            )));
+        }
+
+        let salt: [u8; 32] = general_purpose::STANDARD
+            .decode(&request.create_key_salt)
+            .ok()
+            .and_then(|salt| salt.try_into().ok())
+            .ok_or_else(|| MpcError::InvalidRequest("create_key_salt must be 32 base64-encoded bytes".to_string()))?;
+        let create_key = derive_create_key(&salt, &pubkeys, threshold);
+        let (multisig_address, _) = Pubkey::find_program_address(
+            &[SQUADS_SEED_PREFIX, SQUADS_SEED_MULTISIG, create_key.as_ref()],
+            &self.multisig_program_id,
+        );
+        let (vault_address, _) = Pubkey::find_program_address(
+            &[SQUADS_SEED_PREFIX, multisig_address.as_ref(), SQUADS_SEED_VAULT, &[SQUADS_VAULT_INDEX]],
+            &self.multisig_program_id,
+        );
+
         Ok(AggregateKeysResponse {
-            aggregated_address,
+            aggregated_address: vault_address.to_string(),
+            multisig_address: multisig_address.to_string(),
+            create_key: create_key.to_string(),
+            members: pubkeys.iter().map(Pubkey::to_string).collect(),
+            threshold,
             participant_count: pubkeys.len() as u32,
             created_at: Utc::now().to_rfc3339(),
         })
     }
 
-    pub async fn sign_step_one(&self, request: SignStepOneRequest) -> Result<SignStepOneResponse, Box<dyn std::error::Error>> {
+    /// Opens a signing session bound to an existing key, the exact message
+    /// bytes and the roster of shares expected to sign.
+    pub async fn sign_step_one(&self, request: SignStepOneRequest) -> Result<SignStepOneResponse, MpcError> {
         let signature_id = Uuid::new_v4();
-        
-        // Create transaction data for signing
-        let transaction_data = serde_json::to_string(&request)?;
-        
+        let key_id = Uuid::parse_str(&request.key_id)
+            .map_err(|_| MpcError::KeyNotFound(request.key_id.clone()))?;
+
+        let message = general_purpose::STANDARD
+            .decode(&request.transaction_data)
+            .map_err(|e| MpcError::InvalidRequest(format!("Invalid base64 transaction_data: {e}")))?;
+
+        let key_share_ids = self.engine.share_ids(&request.key_id).await?;
+        let (_, public_key_package) = self.engine.public_keys(&request.key_id).await?;
+
+        // Without an explicit roster every share of the key is expected to sign
+        let share_ids = if request.share_ids.is_empty() {
+            key_share_ids.clone()
+        } else {
+            request.share_ids.clone()
+        };
+
+        let mut unique_share_ids = share_ids.clone();
+        unique_share_ids.sort();
+        unique_share_ids.dedup();
+        if unique_share_ids.len() != share_ids.len() {
+            return Err(MpcError::InvalidRequest("Duplicate share IDs in roster".to_string()));
+        }
+        if let Some(unknown) = share_ids.iter().find(|id| !key_share_ids.contains(id)) {
+            return Err(MpcError::InvalidRequest(format!("Share {unknown} does not belong to key {key_id}")));
+        }
+        if share_ids.len() < public_key_package.threshold as usize {
+            return Err(MpcError::InvalidThreshold(format!(
+                "{} signers provided, key requires {}",
+                share_ids.len(),
+                public_key_package.threshold
+            )));
+        }
+
+        // Expired sessions are evicted first, so abandoned nonces do not pile
+        // up and their spends are back before this message is checked
+        {
+            let mut signatures = self.signatures.write().await;
+            let expired: Vec<Uuid> = signatures
+                .iter()
+                .filter(|(_, signature)| self.is_expired(signature.created_at))
+                .map(|(&id, _)| id)
+                .collect();
+            for id in expired {
+                if let Some(session) = signatures.remove(&id) {
+                    self.discard(session);
+                }
+            }
+        }
+
+        let spend = self.engine.authorize_message(&request.key_id, &message).await?;
+
+        // Round one: every rostered share commits to its nonces
+        let round_one = match self.engine.commit(&request.key_id, &share_ids).await {
+            Ok(round_one) => round_one,
+            Err(e) => {
+                if let Some(spend) = spend {
+                    self.engine.refund(spend);
+                }
+                return Err(e);
+            }
+        };
+
+        let mut nonces = HashMap::new();
+        let mut commitments = BTreeMap::new();
+        let mut participants = BTreeMap::new();
+        for ((identifier, participant_nonces, participant_commitments), share_id) in round_one.into_iter().zip(share_ids) {
+            nonces.insert(identifier, participant_nonces);
+            commitments.insert(identifier, participant_commitments);
+            participants.insert(identifier, share_id);
+        }
+
+        let response = SignStepOneResponse {
+            signature_id: signature_id.to_string(),
+            key_id: key_id.to_string(),
+            transaction_data: request.transaction_data,
+            participants: participants
+                .iter()
+                .map(|(&identifier, share_id)| SessionParticipant {
+                    participant_id: identifier as u32,
+                    share_id: share_id.clone(),
+                })
+                .collect(),
+            created_at: Utc::now().to_rfc3339(),
+        };
+
         // Store the signature session
         let mpc_signature = MpcSignature {
             id: signature_id,
-            key_id: Uuid::new_v4(), // This should be the actual key ID
-            message: transaction_data.as_bytes().to_vec(),
+            key_id,
+            signing_package: SigningPackage::new(commitments, &message),
+            message,
+            participants,
+            nonces,
             partial_signatures: Vec::new(),
-            final_signature: None,
             status: SignatureStatus::Pending,
             created_at: Utc::now(),
+            spend,
         };
-        
-        {
-            let mut signatures = self.signatures.write().await;
-            signatures.insert(signature_id, mpc_signature);
-        }
-        
-        Ok(SignStepOneResponse {
-            signature_id: signature_id.to_string(),
-            transaction_data,
-            participant_id: 0, // This should be determined by the participant
-            created_at: Utc::now().to_rfc3339(),
-        })
+
+        self.signatures.write().await.insert(signature_id, mpc_signature);
+
+        Ok(response)
     }
 
-    pub async fn sign_step_two(&self, request: SignStepTwoRequest) -> Result<SignStepTwoResponse, Box<dyn std::error::Error>> {
-        let signature_id = Uuid::parse_str(&request.signature_id)?;
-        
-        // Get the signature session
-        let mut signature = {
+    /// Produces the signature share of one rostered participant. Anyone
+    /// outside the session roster is refused.
+    pub async fn sign_step_two(&self, request: SignStepTwoRequest) -> Result<SignStepTwoResponse, MpcError> {
+        let signature_id = Uuid::parse_str(&request.signature_id)
+            .map_err(|e| MpcError::InvalidRequest(format!("Invalid signature_id: {e}")))?;
+
+        // Round two: the participant's nonces are taken out of the session,
+        // so the lock is not held while its share is opened
+        let (key_id, share_id, identifier, signing_package, nonces) = {
             let mut signatures = self.signatures.write().await;
-            signatures.get_mut(&signature_id).ok_or("Signature session not found")?.clone()
+            let signature = self.live_session(&mut signatures, &signature_id)?;
+
+            let identifier = Identifier::try_from(request.participant_id)
+                .ok()
+                .filter(|id| signature.participants.contains_key(id))
+                .ok_or(MpcError::ParticipantNotInRoster(request.participant_id))?;
+            let nonces = signature.nonces.remove(&identifier).ok_or_else(|| {
+                MpcError::InvalidRequest(format!("Participant {identifier} has already signed"))
+            })?;
+            (
+                signature.key_id.to_string(),
+                signature.participants[&identifier].clone(),
+                identifier,
+                signature.signing_package.clone(),
+                nonces,
+            )
         };
-        
-        // Decode the private key share
-        let private_key_bytes = bs58::decode(&request.private_key_share).into_vec()?;
-        let keypair = solana_sdk::signer::keypair::Keypair::from_bytes(&private_key_bytes)?;
-        
-        // Create a simple transaction for signing (in real implementation, this would be more complex)
-        let transaction_data = request.transaction_data.as_bytes();
-        let signature_bytes = keypair.sign_message(transaction_data);
-        
+
+        let share = self.engine
+            .sign_share(&key_id, &share_id, identifier, &signing_package, nonces)
+            .await?;
+        let signature_bytes = share.to_bytes();
+
         // Store the partial signature
+        let mut signatures = self.signatures.write().await;
+        let signature = self.live_session(&mut signatures, &signature_id)?;
         signature.partial_signatures.push(PartialSignatureData {
             participant_id: request.participant_id,
-            signature: signature_bytes.to_bytes().to_vec(),
+            signature: signature_bytes.to_vec(),
             created_at: Utc::now(),
         });
-        
+
         signature.status = SignatureStatus::Partial;
-        
-        {
-            let mut signatures = self.signatures.write().await;
-            signatures.insert(signature_id, signature);
-        }
-        
+
         Ok(SignStepTwoResponse {
             signature_id: signature_id.to_string(),
-            partial_signature: bs58::encode(signature_bytes.to_bytes()).into_string(),
+            partial_signature: bs58::encode(signature_bytes).into_string(),
             participant_id: request.participant_id,
             created_at: Utc::now().to_rfc3339(),
         })
     }
 
-    pub async fn aggregate_signatures(&self, request: AggregateSignaturesRequest) -> Result<AggregateSignaturesResponse, Box<dyn std::error::Error>> {
-        let signature_id = Uuid::parse_str(&request.signature_id)?;
-        
-        // Get the signature session
-        let mut signature = {
+    /// Verifies every partial signature against its participant's verifying
+    /// share, combines them and checks the result against the wallet key and
+    /// the session message before completing the session.
+    pub async fn aggregate_signatures(&self, request: AggregateSignaturesRequest) -> Result<AggregateSignaturesResponse, MpcError> {
+        let signature_id = Uuid::parse_str(&request.signature_id)
+            .map_err(|e| MpcError::InvalidRequest(format!("Invalid signature_id: {e}")))?;
+
+        let key_id = {
             let mut signatures = self.signatures.write().await;
-            signatures.get_mut(&signature_id).ok_or("Signature session not found")?.clone()
+            self.live_session(&mut signatures, &signature_id)?.key_id.to_string()
         };
-        
-        // For n-of-n multisig, we need all signatures
-        if request.partial_signatures.len() != signature.partial_signatures.len() {
-            return Err("Not all participants have signed".into());
-        }
-        
-        // In a real implementation, you would properly aggregate the signatures
-        // For now, we'll use the first signature as the final signature
-        let final_signature = if let Some(first_sig) = request.partial_signatures.first() {
-            bs58::decode(&first_sig.signature).into_vec()?
-        } else {
-            return Err("No signatures to aggregate".into());
+        let (verifying_key, public_key_package) = self.engine.public_keys(&key_id).await?;
+
+        let mut signatures = self.signatures.write().await;
+        let signature = self.live_session(&mut signatures, &signature_id)?;
+        let result = combine_partials(signature, &request.partial_signatures, &verifying_key, &public_key_package);
+
+        // The session and whatever nonces are left are no longer needed. A
+        // failed aggregation ends the session too, and its spend is returned
+        let session = signatures.remove(&signature_id).expect("live session");
+        let combined = match result {
+            Ok(combined) => combined,
+            Err(e) => {
+                self.discard(session);
+                return Err(e);
+            }
         };
-        
-        // Update signature status
-        signature.final_signature = Some(final_signature.clone());
-        signature.status = SignatureStatus::Completed;
-        
-        {
-            let mut signatures = self.signatures.write().await;
-            signatures.insert(signature_id, signature);
-        }
-        
-        // In a real implementation, you would broadcast the transaction here
-        let transaction_id = "mock_transaction_id".to_string();
-        
+
+        // Solana identifies a transaction by its first signature
+        let final_signature = bs58::encode(combined).into_string();
+
         Ok(AggregateSignaturesResponse {
             signature_id: signature_id.to_string(),
-            final_signature: bs58::encode(&final_signature).into_string(),
-            transaction_id,
+            transaction_id: final_signature.clone(),
+            final_signature,
             created_at: Utc::now().to_rfc3339(),
         })
     }
 
-    pub async fn get_balance(&self, request: BalanceRequest) -> Result<BalanceResponse, Box<dyn std::error::Error>> {
-        let address = request.address.parse::<Pubkey>()?;
-        let balance = self.rpc_client.get_balance(&address)?;
-        
+    pub async fn get_balance(&self, request: BalanceRequest) -> Result<BalanceResponse, MpcError> {
+        let address = parse_address(&request.address)?;
+        let balance = self.rpc_client
+            .get_balance(&address)
+            .await
+            .map_err(|e| MpcError::RpcError(format!("{e:#}")))?;
+
         Ok(BalanceResponse {
             address: request.address,
             balance,
@@ -215,12 +388,15 @@ impl MpcService {
         })
     }
 
-    pub async fn request_airdrop(&self, request: AirdropRequest) -> Result<AirdropResponse, Box<dyn std::error::Error>> {
-        let address = request.address.parse::<Pubkey>()?;
+    pub async fn request_airdrop(&self, request: AirdropRequest) -> Result<AirdropResponse, MpcError> {
+        let address = parse_address(&request.address)?;
         let amount = request.amount.unwrap_or(1_000_000_000); // Default to 1 SOL
-        
-        let signature = self.rpc_client.request_airdrop(&address, amount)?;
-        
+
+        let signature = self.rpc_client
+            .request_airdrop(&address, amount)
+            .await
+            .map_err(|e| MpcError::RpcError(format!("{e:#}")))?;
+
         Ok(AirdropResponse {
             address: request.address,
             amount,
@@ -229,3 +405,413 @@ impl MpcService {
         })
     }
 }
+
+fn parse_address(address: &str) -> Result<Pubkey, MpcError> {
+    address
+        .parse()
+        .map_err(|e| MpcError::InvalidRequest(format!("Invalid address {address}: {e}")))
+}
+
+/// Public key of the ed25519 keypair whose 32-byte seed is
+/// `sha256(domain || salt || sorted members || threshold as u16 LE)`; the
+/// same seed gives the Solana `Keypair` that signs the multisig creation.
+fn derive_create_key(salt: &[u8; 32], members: &[Pubkey], threshold: u16) -> Pubkey {
+    let mut hasher = Sha256::new();
+    hasher.update(CREATE_KEY_DOMAIN);
+    hasher.update(salt);
+    for member in members {
+        hasher.update(member.as_ref());
+    }
+    hasher.update(threshold.to_le_bytes());
+    let seed: [u8; 32] = hasher.finalize().into();
+    Pubkey::new_from_array(SigningKey::from_bytes(&seed).verifying_key().to_bytes())
+}
+
+fn parse_key(key: &str) -> Result<Pubkey, MpcError> {
+    key.parse()
+        .map_err(|e| MpcError::InvalidRequest(format!("Invalid public key {key}: {e}")))
+}
+
+/// Verifies every partial signature of a session against its participant's
+/// verifying share and combines them into a signature over the session
+/// message.
+fn combine_partials(
+    signature: &MpcSignature,
+    partials: &[PartialSignature],
+    verifying_key: &ed25519_dalek::VerifyingKey,
+    public_key_package: &frost::PublicKeyPackage,
+) -> Result<[u8; 64], MpcError> {
+    // Decode and attribute every partial signature
+    let mut shares = BTreeMap::new();
+    for partial in partials {
+        let identifier = Identifier::try_from(partial.participant_id)
+            .map_err(|_| MpcError::InvalidPartialSignature(partial.participant_id))?;
+        let share = decode_signature_share(&partial.signature)
+            .ok_or(MpcError::InvalidPartialSignature(partial.participant_id))?;
+        frost::verify_signature_share(&signature.signing_package, identifier, &share, public_key_package)
+            .map_err(|_| MpcError::InvalidPartialSignature(partial.participant_id))?;
+
+        if shares.insert(identifier, share).is_some() {
+            return Err(MpcError::InvalidRequest(format!(
+                "Duplicate partial signature from participant {}",
+                partial.participant_id
+            )));
+        }
+    }
+
+    if let Some(missing) = signature.signing_package.participants().into_iter().find(|id| !shares.contains_key(id)) {
+        return Err(MpcError::InvalidRequest(format!("Participant {missing} has not signed")));
+    }
+
+    let combined = frost::aggregate(&signature.signing_package, &shares, public_key_package)
+        .map_err(|e| match e {
+            FrostError::InvalidSignatureShare(id) => MpcError::InvalidPartialSignature(id as u32),
+            other => MpcError::SignatureError(other.to_string()),
+        })?;
+
+    verifying_key
+        .verify(&signature.message, &Signature::from_bytes(&combined))
+        .map_err(|e| MpcError::SignatureError(format!("Aggregated signature verification failed: {e}")))?;
+    Ok(combined)
+}
+
+fn decode_signature_share(encoded: &str) -> Option<Scalar> {
+    let bytes: [u8; 32] = bs58::decode(encoded).into_vec().ok()?.try_into().ok()?;
+    Option::from(Scalar::from_canonical_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service() -> MpcService {
+        service_with_ttl(Duration::from_secs(600))
+    }
+
+    fn service_with_ttl(session_ttl: Duration) -> MpcService {
+        MpcService::new(
+            Arc::new(crate::services::mpc_engine::tests::engine()),
+            "http://localhost:8899".to_string(),
+            Pubkey::new_unique(),
+            session_ttl,
+        )
+    }
+
+    /// Opens a session on a fresh 2-of-3 wallet and collects every partial.
+    async fn signed_session(service: &MpcService) -> (SignStepOneResponse, Vec<PartialSignature>) {
+        let wallet = service.engine.generate_key(2, 3, None, None).await.unwrap();
+        let step_one = service
+            .sign_step_one(SignStepOneRequest {
+                key_id: wallet.wallet_id.clone(),
+                transaction_data: general_purpose::STANDARD.encode(b"payroll batch"),
+                share_ids: Vec::new(),
+            })
+            .await
+            .unwrap();
+
+        let mut partials = Vec::new();
+        for participant_id in 1..=3 {
+            let step_two = service
+                .sign_step_two(SignStepTwoRequest {
+                    signature_id: step_one.signature_id.clone(),
+                    participant_id,
+                })
+                .await
+                .unwrap();
+            partials.push(PartialSignature {
+                participant_id,
+                signature: step_two.partial_signature,
+            });
+        }
+        (step_one, partials)
+    }
+
+    fn aggregate_request(keys: &[Pubkey], salt: [u8; 32]) -> AggregateKeysRequest {
+        AggregateKeysRequest {
+            participant_keys: keys.iter().map(|k| k.to_string()).collect(),
+            create_key_salt: general_purpose::STANDARD.encode(salt),
+            threshold: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn aggregate_keys_derives_the_squads_vault_regardless_of_order() {
+        let service = service();
+        let salt = [7; 32];
+        let keys = [Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()];
+        let reversed = [keys[2], keys[1], keys[0]];
+
+        let forward = service.aggregate_keys(aggregate_request(&keys, salt)).await.unwrap();
+        let backward = service.aggregate_keys(aggregate_request(&reversed, salt)).await.unwrap();
+        assert_eq!(forward.aggregated_address, backward.aggregated_address);
+        assert_eq!(forward.members, backward.members);
+        assert_eq!(forward.threshold, 3);
+
+        let mut sorted = keys.to_vec();
+        sorted.sort();
+        assert_eq!(forward.members, sorted.iter().map(Pubkey::to_string).collect::<Vec<_>>());
+
+        // The create key is the Solana keypair seeded with the derivation hash
+        let mut seed = Sha256::new();
+        seed.update(CREATE_KEY_DOMAIN);
+        seed.update(salt);
+        sorted.iter().for_each(|member| seed.update(member.as_ref()));
+        seed.update(3u16.to_le_bytes());
+        let keypair = solana_sdk::signature::Keypair::new_from_array(seed.finalize().into());
+        let create_key = solana_sdk::signer::Signer::pubkey(&keypair);
+        assert_eq!(forward.create_key, create_key.to_string());
+
+        let (multisig, _) = Pubkey::find_program_address(
+            &[b"multisig", b"multisig", create_key.as_ref()],
+            &service.multisig_program_id,
+        );
+        let (vault, _) = Pubkey::find_program_address(
+            &[b"multisig", multisig.as_ref(), b"vault", &[0]],
+            &service.multisig_program_id,
+        );
+        assert_eq!(forward.multisig_address, multisig.to_string());
+        assert_eq!(forward.aggregated_address, vault.to_string());
+
+        let other = service.aggregate_keys(aggregate_request(&keys, [8; 32])).await.unwrap();
+        assert_ne!(other.aggregated_address, forward.aggregated_address);
+    }
+
+    #[tokio::test]
+    async fn aggregate_keys_binds_the_address_to_members_and_threshold() {
+        let service = service();
+        let salt = [7; 32];
+        let keys = [Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()];
+
+        let original = service.aggregate_keys(aggregate_request(&keys, salt)).await.unwrap();
+        let swapped_member = service
+            .aggregate_keys(aggregate_request(&[keys[0], keys[1], Pubkey::new_unique()], salt))
+            .await
+            .unwrap();
+        let fewer_members = service.aggregate_keys(aggregate_request(&keys[..2], salt)).await.unwrap();
+        let lower_threshold = service
+            .aggregate_keys(AggregateKeysRequest { threshold: Some(2), ..aggregate_request(&keys, salt) })
+            .await
+            .unwrap();
+
+        for other in [swapped_member, fewer_members, lower_threshold] {
+            assert_ne!(other.create_key, original.create_key);
+            assert_ne!(other.multisig_address, original.multisig_address);
+            assert_ne!(other.aggregated_address, original.aggregated_address);
+        }
+
+        let short_salt = AggregateKeysRequest {
+            create_key_salt: general_purpose::STANDARD.encode([1; 16]),
+            ..aggregate_request(&keys, salt)
+        };
+        assert!(matches!(service.aggregate_keys(short_salt).await, Err(MpcError::InvalidRequest(_))));
+    }
+
+    #[tokio::test]
+    async fn aggregate_keys_rejects_empty_and_duplicate_sets() {
+        let service = service();
+        let key = Pubkey::new_unique();
+        let salt = [7; 32];
+
+        assert!(matches!(
+            service.aggregate_keys(aggregate_request(&[], salt)).await,
+            Err(MpcError::EmptyKeySet)
+        ));
+        assert!(matches!(
+            service.aggregate_keys(aggregate_request(&[key, Pubkey::new_unique(), key], salt)).await,
+            Err(MpcError::DuplicateKey(_))
+        ));
+        for threshold in [0, 3] {
+            let request = AggregateKeysRequest {
+                threshold: Some(threshold),
+                ..aggregate_request(&[key, Pubkey::new_unique()], salt)
+            };
+            assert!(matches!(service.aggregate_keys(request).await, Err(MpcError::InvalidThreshold(_))));
+        }
+    }
+
+    #[tokio::test]
+    async fn aggregate_signatures_combines_partials_into_wallet_signature() {
+        let service = service();
+        let (step_one, partials) = signed_session(&service).await;
+
+        let session = service.session(&step_one.signature_id).await.unwrap();
+
+        let response = service
+            .aggregate_signatures(AggregateSignaturesRequest {
+                signature_id: step_one.signature_id.clone(),
+                partial_signatures: partials,
+            })
+            .await
+            .unwrap();
+
+        let (verifying_key, _) = service.engine.public_keys(&session.key_id).await.unwrap();
+        let bytes: [u8; 64] = bs58::decode(&response.final_signature).into_vec().unwrap().try_into().unwrap();
+        assert!(verifying_key.verify_strict(&session.message, &Signature::from_bytes(&bytes)).is_ok());
+
+        // The completed session is dropped
+        assert!(service.signatures.read().await.is_empty());
+        assert!(matches!(
+            service.session(&step_one.signature_id).await,
+            Err(MpcError::SessionNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn expired_sessions_are_refused_and_evicted() {
+        let service = service_with_ttl(Duration::ZERO);
+        let wallet = service.engine.generate_key(2, 3, None, None).await.unwrap();
+        let open = || {
+            service.sign_step_one(SignStepOneRequest {
+                key_id: wallet.wallet_id.clone(),
+                transaction_data: general_purpose::STANDARD.encode(b"payroll batch"),
+                share_ids: Vec::new(),
+            })
+        };
+
+        let stale = open().await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        let result = service
+            .sign_step_two(SignStepTwoRequest {
+                signature_id: stale.signature_id.clone(),
+                participant_id: 1,
+            })
+            .await;
+        assert!(matches!(result, Err(MpcError::SessionNotFound(_))));
+
+        let unknown = service
+            .sign_step_two(SignStepTwoRequest {
+                signature_id: Uuid::new_v4().to_string(),
+                participant_id: 1,
+            })
+            .await;
+        assert!(matches!(unknown, Err(MpcError::SessionNotFound(_))));
+
+        // Opening another session evicts every expired one
+        open().await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        let latest = open().await.unwrap();
+        let signatures = service.signatures.read().await;
+        assert_eq!(signatures.len(), 1);
+        assert!(signatures.contains_key(&Uuid::parse_str(&latest.signature_id).unwrap()));
+    }
+
+    /// A service whose wallets may spend 1_500 lamports a day, with a
+    /// transfer of 1_000 out of `wallet` to open sessions for.
+    async fn capped_service(session_ttl: Duration) -> (MpcService, String, String) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("policies.json");
+        std::fs::write(&path, r#"{ "default": { "daily_spend_cap_lamports": 1500 } }"#).unwrap();
+        let policies = crate::services::policy::PolicyEngine::load(&path).unwrap();
+        let service = MpcService::new(
+            Arc::new(crate::services::mpc_engine::tests::engine_with_policies(policies)),
+            "http://localhost:8899".to_string(),
+            Pubkey::new_unique(),
+            session_ttl,
+        );
+
+        let wallet = service.engine.generate_key(2, 3, None, None).await.unwrap();
+        let payer = Pubkey::new_from_array(wallet.public_key.to_bytes());
+        let transfer = solana_system_interface::instruction::transfer(&payer, &Pubkey::new_unique(), 1_000);
+        let message = solana_sdk::message::Message::new(&[transfer], Some(&payer)).serialize();
+        (service, wallet.wallet_id, general_purpose::STANDARD.encode(message))
+    }
+
+    #[tokio::test]
+    async fn expired_sessions_refund_their_spend() {
+        let (service, wallet_id, transfer) = capped_service(Duration::ZERO).await;
+        let open = || {
+            service.sign_step_one(SignStepOneRequest {
+                key_id: wallet_id.clone(),
+                transaction_data: transfer.clone(),
+                share_ids: Vec::new(),
+            })
+        };
+
+        open().await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        // The first transfer never got signed, so a second one fits the cap
+        open().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn failed_aggregation_refunds_its_spend() {
+        let (service, wallet_id, transfer) = capped_service(Duration::from_secs(600)).await;
+        let open = || {
+            service.sign_step_one(SignStepOneRequest {
+                key_id: wallet_id.clone(),
+                transaction_data: transfer.clone(),
+                share_ids: Vec::new(),
+            })
+        };
+
+        let session = open().await.unwrap();
+        assert!(matches!(open().await, Err(MpcError::PolicyViolation(_))));
+
+        let result = service
+            .aggregate_signatures(AggregateSignaturesRequest {
+                signature_id: session.signature_id,
+                partial_signatures: Vec::new(),
+            })
+            .await;
+        assert!(result.is_err());
+        open().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn aggregate_signatures_attributes_invalid_partials() {
+        let service = service();
+        let (step_one, mut partials) = signed_session(&service).await;
+        partials[1].signature = partials[0].signature.clone();
+
+        let result = service
+            .aggregate_signatures(AggregateSignaturesRequest {
+                signature_id: step_one.signature_id,
+                partial_signatures: partials,
+            })
+            .await;
+
+        assert!(matches!(result, Err(MpcError::InvalidPartialSignature(2))));
+    }
+
+    #[tokio::test]
+    async fn sign_step_one_binds_key_message_and_roster() {
+        let service = service();
+        let wallet = service.engine.generate_key(2, 3, None, None).await.unwrap();
+
+        let unknown_key = service
+            .sign_step_one(SignStepOneRequest {
+                key_id: Uuid::new_v4().to_string(),
+                transaction_data: general_purpose::STANDARD.encode(b"payroll batch"),
+                share_ids: Vec::new(),
+            })
+            .await;
+        assert!(matches!(unknown_key, Err(MpcError::KeyNotFound(_))));
+
+        let step_one = service
+            .sign_step_one(SignStepOneRequest {
+                key_id: wallet.wallet_id.clone(),
+                transaction_data: general_purpose::STANDARD.encode(b"payroll batch"),
+                share_ids: vec![wallet.shares[0].share_id.clone(), wallet.shares[1].share_id.clone()],
+            })
+            .await
+            .unwrap();
+        let participant_ids: Vec<u32> = step_one.participants.iter().map(|p| p.participant_id).collect();
+        assert_eq!(participant_ids, vec![1, 2]);
+
+        let signature_id = Uuid::parse_str(&step_one.signature_id).unwrap();
+        {
+            let signatures = service.signatures.read().await;
+            let session = &signatures[&signature_id];
+            assert_eq!(session.key_id.to_string(), wallet.wallet_id);
+            assert_eq!(session.message, b"payroll batch");
+        }
+
+        let outsider = service
+            .sign_step_two(SignStepTwoRequest {
+                signature_id: step_one.signature_id.clone(),
+                participant_id: 3,
+            })
+            .await;
+        assert!(matches!(outsider, Err(MpcError::ParticipantNotInRoster(3))));
+    }
+}
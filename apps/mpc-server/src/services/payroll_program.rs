@@ -0,0 +1,310 @@
+use borsh::BorshDeserialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use solana_sdk::pubkey::Pubkey;
+
+/// Program id `payroll_solana` is deployed under unless `PAYROLL_PROGRAM_ID`
+/// says otherwise.
+pub const PROGRAM_ID: Pubkey = solana_sdk::pubkey!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+
+/// Mirror of the program's `EmployeePayment` instruction argument.
+#[derive(Debug, Clone, PartialEq, BorshDeserialize)]
+pub struct EmployeePayment {
+    pub employee: Pubkey,
+    pub wallet: Pubkey,
+    pub amount: u64,
+    pub payment_token: Pubkey,
+    pub token_account: Option<Pubkey>,
+}
+
+/// Decoded `payroll_solana` instruction arguments.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PayrollInstruction {
+    InitializeOrganization { name: String, authorized_signers: Vec<Pubkey> },
+    AddEmployee { salary: u64, payment_token: Pubkey },
+    DeactivateEmployee,
+    SchedulePayroll { run_id: u64, total_amount: u64 },
+    ExecutePayroll { employees: Vec<EmployeePayment> },
+    ClosePayrollRun,
+}
+
+#[derive(BorshDeserialize)]
+struct InitializeOrganizationArgs {
+    name: String,
+    authorized_signers: Vec<Pubkey>,
+}
+
+#[derive(BorshDeserialize)]
+struct AddEmployeeArgs {
+    salary: u64,
+    payment_token: Pubkey,
+}
+
+#[derive(BorshDeserialize)]
+struct SchedulePayrollArgs {
+    run_id: u64,
+    total_amount: u64,
+}
+
+#[derive(BorshDeserialize)]
+struct ExecutePayrollArgs {
+    employees: Vec<EmployeePayment>,
+}
+
+impl PayrollInstruction {
+    const NAMES: [&'static str; 6] = [
+        "initialize_organization",
+        "add_employee",
+        "deactivate_employee",
+        "schedule_payroll",
+        "execute_payroll",
+        "close_payroll_run",
+    ];
+
+    /// Decodes instruction data: the 8-byte Anchor discriminator followed by
+    /// the Borsh-encoded arguments, with no trailing bytes.
+    pub fn decode(data: &[u8]) -> Result<Self, String> {
+        let (tag, mut args) = data
+            .split_at_checked(8)
+            .ok_or_else(|| "instruction data shorter than a discriminator".to_string())?;
+        let name = Self::NAMES
+            .into_iter()
+            .find(|name| discriminator("global", name) == tag)
+            .ok_or_else(|| format!("unknown instruction discriminator {}", hex::encode(tag)))?;
+
+        let args = &mut args;
+        let instruction = match name {
+            "initialize_organization" => {
+                let InitializeOrganizationArgs { name, authorized_signers } = borsh_args(args)?;
+                Self::InitializeOrganization { name, authorized_signers }
+            }
+            "add_employee" => {
+                let AddEmployeeArgs { salary, payment_token } = borsh_args(args)?;
+                Self::AddEmployee { salary, payment_token }
+            }
+            "deactivate_employee" => Self::DeactivateEmployee,
+            "schedule_payroll" => {
+                let SchedulePayrollArgs { run_id, total_amount } = borsh_args(args)?;
+                Self::SchedulePayroll { run_id, total_amount }
+            }
+            "execute_payroll" => {
+                let ExecutePayrollArgs { employees } = borsh_args(args)?;
+                Self::ExecutePayroll { employees }
+            }
+            "close_payroll_run" => Self::ClosePayrollRun,
+            _ => unreachable!("every name in NAMES is decoded"),
+        };
+        if !args.is_empty() {
+            return Err(format!("{name} has {} trailing bytes", args.len()));
+        }
+        Ok(instruction)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::InitializeOrganization { .. } => "initialize_organization",
+            Self::AddEmployee { .. } => "add_employee",
+            Self::DeactivateEmployee => "deactivate_employee",
+            Self::SchedulePayroll { .. } => "schedule_payroll",
+            Self::ExecutePayroll { .. } => "execute_payroll",
+            Self::ClosePayrollRun => "close_payroll_run",
+        }
+    }
+
+    /// Position of the payroll run account among the instruction accounts.
+    pub fn payroll_run_account(&self) -> Option<usize> {
+        match self {
+            Self::SchedulePayroll { .. } | Self::ExecutePayroll { .. } | Self::ClosePayrollRun => Some(1),
+            _ => None,
+        }
+    }
+
+    /// Position of the optional `payment_mint` account of `schedule_payroll`.
+    /// Anchor passes the program id in place of an omitted optional account.
+    pub fn payment_mint_account(&self) -> Option<usize> {
+        match self {
+            Self::SchedulePayroll { .. } => Some(5),
+            _ => None,
+        }
+    }
+
+    /// Amount an `execute_payroll` instruction pays out, in the run's
+    /// payment mint.
+    pub fn payout_total(&self) -> Option<u64> {
+        match self {
+            Self::ExecutePayroll { employees } => {
+                Some(employees.iter().fold(0u64, |total, payment| total.saturating_add(payment.amount)))
+            }
+            _ => None,
+        }
+    }
+
+    /// JSON description of the instruction for audit records.
+    pub fn summary(&self, payroll_run: Option<&Pubkey>) -> Value {
+        let mut summary = match self {
+            Self::InitializeOrganization { name, authorized_signers } => json!({
+                "name": name,
+                "authorized_signers": authorized_signers.iter().map(Pubkey::to_string).collect::<Vec<_>>(),
+            }),
+            Self::AddEmployee { salary, payment_token } => json!({
+                "salary": salary,
+                "payment_token": payment_token.to_string(),
+            }),
+            Self::DeactivateEmployee => json!({}),
+            Self::SchedulePayroll { run_id, total_amount } => json!({
+                "run_id": run_id,
+                "total_amount": total_amount,
+            }),
+            Self::ExecutePayroll { employees } => json!({
+                "total": self.payout_total(),
+                "employees": employees.iter().map(|payment| json!({
+                    "employee": payment.employee.to_string(),
+                    "wallet": payment.wallet.to_string(),
+                    "amount": payment.amount,
+                    "payment_token": payment.payment_token.to_string(),
+                    "token_account": payment.token_account.map(|account| account.to_string()),
+                })).collect::<Vec<_>>(),
+            }),
+            Self::ClosePayrollRun => json!({}),
+        };
+        summary["instruction"] = json!(self.name());
+        if let Some(payroll_run) = payroll_run {
+            summary["payroll_run"] = json!(payroll_run.to_string());
+        }
+        summary
+    }
+}
+
+/// On-chain `PayrollRunAccount` state.
+#[derive(Debug, Clone, PartialEq, BorshDeserialize)]
+pub struct PayrollRun {
+    pub organization: Pubkey,
+    pub run_id: u64,
+    pub total_employees: u16,
+    pub total_amount: u64,
+    pub status: u8,
+    pub escrow_account: Pubkey,
+    pub created_at: i64,
+    pub bump: u8,
+    /// System program id for SOL runs
+    pub payment_mint: Pubkey,
+    pub fees_withheld: u64,
+    pub escrow_bump: u8,
+    pub total_disbursed: u64,
+}
+
+impl PayrollRun {
+    /// Decodes account data behind the `PayrollRunAccount` discriminator.
+    /// Trailing bytes are allocation padding and are ignored.
+    pub fn decode(data: &[u8]) -> Result<Self, String> {
+        let (tag, mut fields) = data
+            .split_at_checked(8)
+            .ok_or_else(|| "account data shorter than a discriminator".to_string())?;
+        if tag != discriminator("account", "PayrollRunAccount") {
+            return Err("not a PayrollRunAccount".to_string());
+        }
+        Self::deserialize(&mut fields).map_err(|e| format!("invalid PayrollRunAccount: {e}"))
+    }
+}
+
+/// Anchor discriminator: the first 8 bytes of `sha256("<namespace>:<name>")`.
+fn discriminator(namespace: &str, name: &str) -> [u8; 8] {
+    let hash = Sha256::digest(format!("{namespace}:{name}"));
+    hash[..8].try_into().unwrap()
+}
+
+fn borsh_args<T: BorshDeserialize>(args: &mut &[u8]) -> Result<T, String> {
+    T::deserialize(args).map_err(|e| format!("invalid instruction arguments: {e}"))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Instruction data for `name` with Borsh-encoded `args`.
+    pub(crate) fn instruction_data(name: &str, args: &[u8]) -> Vec<u8> {
+        let mut data = discriminator("global", name).to_vec();
+        data.extend_from_slice(args);
+        data
+    }
+
+    pub(crate) fn execute_payroll_data(payments: &[(Pubkey, u64)]) -> Vec<u8> {
+        let mut args = (payments.len() as u32).to_le_bytes().to_vec();
+        for (wallet, amount) in payments {
+            args.extend_from_slice(Pubkey::new_unique().as_ref());
+            args.extend_from_slice(wallet.as_ref());
+            args.extend_from_slice(&amount.to_le_bytes());
+            args.extend_from_slice(solana_system_interface::program::ID.as_ref());
+            args.push(0);
+        }
+        instruction_data("execute_payroll", &args)
+    }
+
+    #[test]
+    fn decodes_instructions_by_discriminator() {
+        let mut args = 7u64.to_le_bytes().to_vec();
+        args.extend_from_slice(&5_000u64.to_le_bytes());
+        assert_eq!(
+            PayrollInstruction::decode(&instruction_data("schedule_payroll", &args)).unwrap(),
+            PayrollInstruction::SchedulePayroll { run_id: 7, total_amount: 5_000 }
+        );
+
+        let signer = Pubkey::new_unique();
+        let mut args = 4u32.to_le_bytes().to_vec();
+        args.extend_from_slice(b"Acme");
+        args.extend_from_slice(&1u32.to_le_bytes());
+        args.extend_from_slice(signer.as_ref());
+        assert_eq!(
+            PayrollInstruction::decode(&instruction_data("initialize_organization", &args)).unwrap(),
+            PayrollInstruction::InitializeOrganization { name: "Acme".to_string(), authorized_signers: vec![signer] }
+        );
+
+        let wallet = Pubkey::new_unique();
+        let execute = PayrollInstruction::decode(&execute_payroll_data(&[(wallet, 1_000), (wallet, 2_500)])).unwrap();
+        assert_eq!(execute.payout_total(), Some(3_500));
+        let summary = execute.summary(Some(&signer));
+        assert_eq!(summary["instruction"], "execute_payroll");
+        assert_eq!(summary["employees"][1]["wallet"], wallet.to_string());
+
+        assert_eq!(
+            PayrollInstruction::decode(&instruction_data("close_payroll_run", &[])).unwrap(),
+            PayrollInstruction::ClosePayrollRun
+        );
+        assert!(PayrollInstruction::decode(&instruction_data("close_payroll_run", &[0])).is_err());
+        assert_eq!(
+            PayrollInstruction::decode(&instruction_data("deactivate_employee", &[])).unwrap(),
+            PayrollInstruction::DeactivateEmployee
+        );
+        assert!(PayrollInstruction::decode(&instruction_data("drain_escrow", &[])).is_err());
+        assert!(PayrollInstruction::decode(&instruction_data("schedule_payroll", &[1, 2])).is_err());
+    }
+
+    /// `PayrollRunAccount` data of a SOL run.
+    pub(crate) fn payroll_run_data(organization: &Pubkey, total_amount: u64, total_disbursed: u64) -> Vec<u8> {
+        let mut data = discriminator("account", "PayrollRunAccount").to_vec();
+        data.extend_from_slice(organization.as_ref());
+        data.extend_from_slice(&9u64.to_le_bytes());
+        data.extend_from_slice(&0u16.to_le_bytes());
+        data.extend_from_slice(&total_amount.to_le_bytes());
+        data.push(0);
+        data.extend_from_slice(Pubkey::new_unique().as_ref());
+        data.extend_from_slice(&1_700_000_000i64.to_le_bytes());
+        data.push(254);
+        data.extend_from_slice(solana_system_interface::program::ID.as_ref());
+        data.extend_from_slice(&0u64.to_le_bytes());
+        data.push(253);
+        data.extend_from_slice(&total_disbursed.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn decodes_payroll_run_account() {
+        let organization = Pubkey::new_unique();
+        let data = payroll_run_data(&organization, 40_000, 15_000);
+
+        let run = PayrollRun::decode(&data).unwrap();
+        assert_eq!((run.organization, run.run_id, run.total_amount), (organization, 9, 40_000));
+        assert_eq!((run.payment_mint, run.total_disbursed), (solana_system_interface::program::ID, 15_000));
+        assert!(PayrollRun::decode(&data[8..]).is_err());
+    }
+}
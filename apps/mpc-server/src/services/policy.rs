@@ -0,0 +1,788 @@
+use anyhow::{Context, Result};
+use chrono::{NaiveDate, Utc};
+use serde::{Deserialize, Deserializer};
+use solana_sdk::instruction::CompiledInstruction;
+use solana_sdk::message::VersionedMessage;
+use solana_sdk::pubkey::Pubkey;
+use solana_system_interface::instruction::SystemInstruction;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+use crate::config::Config;
+use crate::errors::MpcError;
+use crate::services::payroll_program::{self, PayrollInstruction, PayrollRun};
+use crate::services::solana_rpc::SolanaRpc;
+
+const SPL_TOKEN_PROGRAM_ID: Pubkey = solana_sdk::pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+const SPL_TOKEN_2022_PROGRAM_ID: Pubkey = solana_sdk::pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+
+/// Limits on the transactions a wallet may sign. Unset fields do not
+/// restrict anything.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WalletPolicy {
+    /// Programs every instruction must target.
+    #[serde(default, deserialize_with = "pubkey_set")]
+    pub allowed_programs: Option<HashSet<Pubkey>>,
+    /// Largest single System program transfer.
+    #[serde(default)]
+    pub max_lamports_per_transfer: Option<u64>,
+    /// Accounts that System and SPL Token transfers may credit.
+    #[serde(default, deserialize_with = "pubkey_set")]
+    pub allowed_destinations: Option<HashSet<Pubkey>>,
+    /// Lamports the wallet may transfer per UTC day.
+    #[serde(default)]
+    pub daily_spend_cap_lamports: Option<u64>,
+    /// Limits on token transfers, by mint. When set, tokens of other mints
+    /// may not be transferred.
+    #[serde(default, deserialize_with = "pubkey_map")]
+    pub token_limits: Option<HashMap<Pubkey, TokenLimits>>,
+    /// Rejects `execute_payroll` paying out more than is left of the run's
+    /// scheduled `total_amount`.
+    #[serde(default)]
+    pub cap_payroll_at_scheduled_total: bool,
+}
+
+/// Limits on transfers of one token mint, in its base units.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TokenLimits {
+    /// Largest single transfer.
+    #[serde(default)]
+    pub max_per_transfer: Option<u64>,
+    /// Amount the wallet may transfer per UTC day.
+    #[serde(default)]
+    pub daily_spend_cap: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PolicyFile {
+    /// Applies to wallets without an entry of their own.
+    #[serde(default)]
+    default: Option<WalletPolicy>,
+    #[serde(default)]
+    wallets: HashMap<String, WalletPolicy>,
+}
+
+/// Transferred amounts by mint, `None` for lamports.
+type Amounts = HashMap<Option<Pubkey>, u64>;
+
+/// Amounts counted against a wallet's daily caps by an authorized message.
+#[derive(Debug)]
+pub struct Spend {
+    wallet_id: String,
+    day: NaiveDate,
+    amounts: Amounts,
+}
+
+/// Checks messages against the policy of the wallet signing them before any
+/// share is used. Wallets with a policy only sign Solana transaction messages.
+pub struct PolicyEngine {
+    policies: PolicyFile,
+    payroll_program_id: Pubkey,
+    /// Source of on-chain payroll runs; without it only runs scheduled in
+    /// the same message can be checked.
+    rpc: Option<SolanaRpc>,
+    /// Amounts authorized per wallet and mint on the given UTC day.
+    spent: Mutex<HashMap<String, (NaiveDate, Amounts)>>,
+}
+
+impl Default for PolicyEngine {
+    fn default() -> Self {
+        Self {
+            policies: PolicyFile::default(),
+            payroll_program_id: payroll_program::PROGRAM_ID,
+            rpc: None,
+            spent: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+/// Value-moving instruction: the amount it transfers, the mint of a token
+/// transfer (`None` for lamports) and the instruction account credited, if
+/// it is not a program-owned escrow.
+struct Transfer {
+    amount: u64,
+    mint: Option<Pubkey>,
+    destination: Option<usize>,
+}
+
+/// Payroll instructions of one message touching a payroll run.
+#[derive(Default)]
+struct RunActivity {
+    /// `total_amount` of a `schedule_payroll` in the same message.
+    scheduled_total: Option<u64>,
+    /// Sum of the message's `execute_payroll` payouts, in the run's mint.
+    payout_total: u64,
+}
+
+impl PolicyEngine {
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let engine = match &config.policy_file {
+            Some(path) => Self::load(path)?,
+            None => {
+                log::warn!("MPC_POLICY_FILE is not set; signing is not restricted by any wallet policy");
+                Self::default()
+            }
+        };
+        let payroll_program_id = Pubkey::from_str(&config.payroll_program_id)
+            .with_context(|| format!("invalid PAYROLL_PROGRAM_ID {}", config.payroll_program_id))?;
+
+        Ok(Self {
+            payroll_program_id,
+            rpc: Some(SolanaRpc::new(config.solana_rpc_url.clone())),
+            ..engine
+        })
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let contents = std::fs::read(path).with_context(|| format!("reading policy file {}", path.display()))?;
+        let policies: PolicyFile = serde_json::from_slice(&contents)
+            .with_context(|| format!("parsing policy file {}", path.display()))?;
+        log::info!("Loaded signing policies for {} wallets from {}", policies.wallets.len(), path.display());
+
+        Ok(Self {
+            policies,
+            ..Self::default()
+        })
+    }
+
+    fn policy(&self, wallet_id: &str) -> Option<&WalletPolicy> {
+        self.policies.wallets.get(wallet_id).or(self.policies.default.as_ref())
+    }
+
+    /// Checks `message` against the wallet's policy and counts its transfers
+    /// against the daily cap. Violations are logged and rejected with
+    /// [`MpcError::PolicyViolation`].
+    pub async fn authorize(&self, wallet_id: &str, message: &[u8]) -> Result<Option<Spend>, MpcError> {
+        let Some(policy) = self.policy(wallet_id) else {
+            return Ok(None);
+        };
+
+        let result = self.evaluate(policy, wallet_id, message).await;
+        if let Err(MpcError::PolicyViolation(reason)) = &result {
+            log::warn!(
+                target: "mpc_server::audit",
+                "event=policy_violation wallet_id={wallet_id} reason=\"{reason}\""
+            );
+        }
+        result.map(Some)
+    }
+
+    /// Returns the amounts of a message that was authorized but not signed.
+    pub fn refund(&self, spend: Spend) {
+        let mut spent = self.spent.lock().unwrap();
+        if let Some((day, totals)) = spent.get_mut(&spend.wallet_id) {
+            if *day == spend.day {
+                for (mint, amount) in spend.amounts {
+                    if let Some(total) = totals.get_mut(&mint) {
+                        *total = total.saturating_sub(amount);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Decoded `payroll_solana` instructions of a message, for audit records.
+    /// Empty for messages that are not Solana transaction messages.
+    pub fn describe(&self, message: &[u8]) -> Vec<serde_json::Value> {
+        let Some(message) = decode_message(message) else {
+            return Vec::new();
+        };
+        let account_keys = message.static_account_keys();
+
+        message
+            .instructions()
+            .iter()
+            .filter(|instruction| *instruction.program_id(account_keys) == self.payroll_program_id)
+            .map(|instruction| match PayrollInstruction::decode(&instruction.data) {
+                Ok(decoded) => {
+                    let payroll_run = decoded
+                        .payroll_run_account()
+                        .and_then(|position| account_key(account_keys, instruction, position));
+                    decoded.summary(payroll_run)
+                }
+                Err(e) => serde_json::json!({ "instruction": "unknown", "error": e }),
+            })
+            .collect()
+    }
+
+    async fn evaluate(&self, policy: &WalletPolicy, wallet_id: &str, message: &[u8]) -> Result<Spend, MpcError> {
+        let message = decode_message(message)
+            .ok_or_else(|| violation("message is not a Solana transaction message".to_string()))?;
+        let account_keys = message.static_account_keys();
+
+        let mut amounts = Amounts::new();
+        let mut payroll_runs: HashMap<Pubkey, RunActivity> = HashMap::new();
+        for instruction in message.instructions() {
+            let program_id = instruction.program_id(account_keys);
+            if let Some(allowed) = &policy.allowed_programs {
+                if !allowed.contains(program_id) {
+                    return Err(violation(format!("program {program_id} is not allowed")));
+                }
+            }
+
+            let transfer = if *program_id == self.payroll_program_id {
+                let decoded = PayrollInstruction::decode(&instruction.data)
+                    .map_err(|e| violation(format!("undecodable payroll_solana instruction: {e}")))?;
+                payroll_activity(&decoded, account_keys, instruction, &mut payroll_runs)?;
+                self.payroll_transfer(&decoded, account_keys, instruction)?
+            } else {
+                transfer(program_id, account_keys, instruction).map_err(violation)?
+            };
+            let Some(transfer) = transfer else {
+                continue;
+            };
+
+            match transfer.mint {
+                None => {
+                    if let Some(max) = policy.max_lamports_per_transfer {
+                        if transfer.amount > max {
+                            return Err(violation(format!(
+                                "transfer of {} lamports exceeds the per-transfer limit of {max}",
+                                transfer.amount
+                            )));
+                        }
+                    }
+                }
+                Some(mint) => {
+                    let limits = token_limits(policy, &mint)?;
+                    if let Some(max) = limits.and_then(|limits| limits.max_per_transfer) {
+                        if transfer.amount > max {
+                            return Err(violation(format!(
+                                "transfer of {} of mint {mint} exceeds the per-transfer limit of {max}",
+                                transfer.amount
+                            )));
+                        }
+                    }
+                }
+            }
+            if let (Some(allowed), Some(position)) = (&policy.allowed_destinations, transfer.destination) {
+                // Accounts loaded from address lookup tables cannot be resolved here
+                let destination = account_key(account_keys, instruction, position)
+                    .ok_or_else(|| violation("transfer destination is not a static account key".to_string()))?;
+                if !allowed.contains(destination) {
+                    return Err(violation(format!("destination {destination} is not allowed")));
+                }
+            }
+            let total = amounts.entry(transfer.mint).or_default();
+            *total = total.saturating_add(transfer.amount);
+        }
+
+        if policy.cap_payroll_at_scheduled_total {
+            for (payroll_run, activity) in &payroll_runs {
+                if activity.payout_total == 0 {
+                    continue;
+                }
+                // A run scheduled in this message has paid nothing yet
+                let remaining = match activity.scheduled_total {
+                    Some(total) => total,
+                    None => self.remaining_total(payroll_run).await?,
+                };
+                if activity.payout_total > remaining {
+                    return Err(violation(format!(
+                        "execute_payroll pays out {}, payroll run {payroll_run} has {remaining} left to disburse",
+                        activity.payout_total
+                    )));
+                }
+            }
+        }
+
+        let today = Utc::now().date_naive();
+        let mut spent = self.spent.lock().unwrap();
+        let entry = spent.entry(wallet_id.to_string()).or_insert((today, HashMap::new()));
+        if entry.0 != today {
+            *entry = (today, HashMap::new());
+        }
+        for (mint, &amount) in &amounts {
+            let cap = match mint {
+                None => policy.daily_spend_cap_lamports,
+                Some(mint) => token_limits(policy, mint)?.and_then(|limits| limits.daily_spend_cap),
+            };
+            let Some(cap) = cap else {
+                continue;
+            };
+            let already = entry.1.get(mint).copied().unwrap_or_default();
+            if already.saturating_add(amount) > cap {
+                let unit = mint.map_or("lamports".to_string(), |mint| format!("of mint {mint}"));
+                return Err(violation(format!(
+                    "daily spend cap of {cap} {unit} exceeded: {already} already spent today, {amount} requested"
+                )));
+            }
+        }
+        for (mint, &amount) in &amounts {
+            let total = entry.1.entry(*mint).or_default();
+            *total = total.saturating_add(amount);
+        }
+
+        Ok(Spend {
+            wallet_id: wallet_id.to_string(),
+            day: today,
+            amounts,
+        })
+    }
+
+    /// Part of an on-chain payroll run's `total_amount` not yet disbursed.
+    async fn remaining_total(&self, payroll_run: &Pubkey) -> Result<u64, MpcError> {
+        let rpc = self
+            .rpc
+            .as_ref()
+            .ok_or_else(|| violation(format!("payroll run {payroll_run} cannot be looked up")))?;
+        let account = rpc
+            .get_account(payroll_run)
+            .await
+            .map_err(|e| MpcError::RpcError(format!("{e:#}")))?
+            .ok_or_else(|| violation(format!("payroll run {payroll_run} does not exist")))?;
+        if account.owner != self.payroll_program_id {
+            return Err(violation(format!("payroll run {payroll_run} is not owned by payroll_solana")));
+        }
+        let run = PayrollRun::decode(&account.data).map_err(|e| violation(format!("payroll run {payroll_run}: {e}")))?;
+        Ok(run.total_amount.saturating_sub(run.total_disbursed))
+    }
+
+    /// `schedule_payroll` funds the run escrow from the signing authority,
+    /// in lamports or in the run's `payment_mint`.
+    fn payroll_transfer(
+        &self,
+        instruction: &PayrollInstruction,
+        account_keys: &[Pubkey],
+        compiled: &CompiledInstruction,
+    ) -> Result<Option<Transfer>, MpcError> {
+        let PayrollInstruction::SchedulePayroll { total_amount, .. } = instruction else {
+            return Ok(None);
+        };
+        let mint = match instruction.payment_mint_account() {
+            Some(position) if position < compiled.accounts.len() => {
+                let mint = account_key(account_keys, compiled, position)
+                    .ok_or_else(|| violation("schedule_payroll payment mint is not a static account key".to_string()))?;
+                (*mint != self.payroll_program_id).then_some(*mint)
+            }
+            _ => None,
+        };
+
+        Ok(Some(Transfer {
+            amount: *total_amount,
+            mint,
+            destination: None,
+        }))
+    }
+}
+
+fn violation(reason: String) -> MpcError {
+    MpcError::PolicyViolation(reason)
+}
+
+/// Parses legacy and v0 message bytes, accepting only their exact encoding.
+fn decode_message(bytes: &[u8]) -> Option<VersionedMessage> {
+    let message: VersionedMessage = bincode::deserialize(bytes).ok()?;
+    message.sanitize().ok()?;
+    (message.serialize() == bytes).then_some(message)
+}
+
+fn account_key<'a>(account_keys: &'a [Pubkey], instruction: &CompiledInstruction, position: usize) -> Option<&'a Pubkey> {
+    instruction
+        .accounts
+        .get(position)
+        .and_then(|&index| account_keys.get(index as usize))
+}
+
+/// Decodes System and token program instructions. Instructions of these
+/// programs that are not plain transfers or account funding are rejected,
+/// since they could hand over control of the wallet or its token accounts.
+fn transfer(
+    program_id: &Pubkey,
+    account_keys: &[Pubkey],
+    instruction: &CompiledInstruction,
+) -> Result<Option<Transfer>, String> {
+    let to = |amount, position| Some(Transfer { amount, mint: None, destination: Some(position) });
+
+    if *program_id == solana_system_interface::program::ID {
+        let decoded: SystemInstruction = bincode::deserialize(&instruction.data)
+            .map_err(|e| format!("undecodable System instruction: {e}"))?;
+        return match decoded {
+            SystemInstruction::CreateAccount { lamports, .. } => Ok(to(lamports, 1)),
+            SystemInstruction::CreateAccountWithSeed { lamports, .. } => Ok(to(lamports, 1)),
+            SystemInstruction::Transfer { lamports } => Ok(to(lamports, 1)),
+            SystemInstruction::TransferWithSeed { lamports, .. } => Ok(to(lamports, 2)),
+            SystemInstruction::AdvanceNonceAccount => Ok(None),
+            other => Err(format!("System instruction {} is not allowed", system_instruction_name(&other))),
+        };
+    }
+
+    if *program_id == SPL_TOKEN_PROGRAM_ID || *program_id == SPL_TOKEN_2022_PROGRAM_ID {
+        let token_2022 = *program_id == SPL_TOKEN_2022_PROGRAM_ID;
+        // TransferChecked and TransferCheckedWithFee: [source, mint, destination, authority]
+        let amount = match instruction.data.as_slice() {
+            [12, rest @ ..] if rest.len() == 9 => rest,
+            [26, 1, rest @ ..] if token_2022 && rest.len() == 17 => rest,
+            [3, ..] => return Err("token Transfer does not name its mint; use TransferChecked".to_string()),
+            [] => return Err("empty token instruction".to_string()),
+            [tag, ..] => return Err(format!("token instruction {tag} is not allowed")),
+        };
+        let amount = u64::from_le_bytes(amount[..8].try_into().unwrap());
+        let mint = account_key(account_keys, instruction, 1)
+            .ok_or_else(|| "token transfer mint is not a static account key".to_string())?;
+        return Ok(Some(Transfer {
+            amount,
+            mint: Some(*mint),
+            destination: Some(2),
+        }));
+    }
+
+    Ok(None)
+}
+
+fn system_instruction_name(instruction: &SystemInstruction) -> &'static str {
+    match instruction {
+        SystemInstruction::CreateAccount { .. } => "CreateAccount",
+        SystemInstruction::Assign { .. } => "Assign",
+        SystemInstruction::Transfer { .. } => "Transfer",
+        SystemInstruction::CreateAccountWithSeed { .. } => "CreateAccountWithSeed",
+        SystemInstruction::AdvanceNonceAccount => "AdvanceNonceAccount",
+        SystemInstruction::WithdrawNonceAccount(_) => "WithdrawNonceAccount",
+        SystemInstruction::InitializeNonceAccount(_) => "InitializeNonceAccount",
+        SystemInstruction::AuthorizeNonceAccount(_) => "AuthorizeNonceAccount",
+        SystemInstruction::Allocate { .. } => "Allocate",
+        SystemInstruction::AllocateWithSeed { .. } => "AllocateWithSeed",
+        SystemInstruction::AssignWithSeed { .. } => "AssignWithSeed",
+        SystemInstruction::TransferWithSeed { .. } => "TransferWithSeed",
+        SystemInstruction::UpgradeNonceAccount => "UpgradeNonceAccount",
+    }
+}
+
+/// Limits for `mint`, rejecting mints the policy does not list.
+fn token_limits<'a>(policy: &'a WalletPolicy, mint: &Pubkey) -> Result<Option<&'a TokenLimits>, MpcError> {
+    match &policy.token_limits {
+        None => Ok(None),
+        Some(limits) => limits
+            .get(mint)
+            .map(Some)
+            .ok_or_else(|| violation(format!("transfers of mint {mint} are not allowed"))),
+    }
+}
+
+fn payroll_activity(
+    instruction: &PayrollInstruction,
+    account_keys: &[Pubkey],
+    compiled: &CompiledInstruction,
+    payroll_runs: &mut HashMap<Pubkey, RunActivity>,
+) -> Result<(), MpcError> {
+    let Some(position) = instruction.payroll_run_account() else {
+        return Ok(());
+    };
+    let payroll_run = account_key(account_keys, compiled, position)
+        .ok_or_else(|| violation(format!("{} payroll run is not a static account key", instruction.name())))?;
+    let activity = payroll_runs.entry(*payroll_run).or_default();
+
+    match instruction {
+        PayrollInstruction::SchedulePayroll { total_amount, .. } => activity.scheduled_total = Some(*total_amount),
+        PayrollInstruction::ExecutePayroll { .. } => {
+            let payout = instruction.payout_total().unwrap_or_default();
+            activity.payout_total = activity.payout_total.saturating_add(payout);
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn pubkey_set<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<HashSet<Pubkey>>, D::Error> {
+    let Some(keys) = Option::<Vec<String>>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+    keys.iter()
+        .map(|key| Pubkey::from_str(key).map_err(|e| serde::de::Error::custom(format!("invalid pubkey {key}: {e}"))))
+        .collect::<Result<_, _>>()
+        .map(Some)
+}
+
+fn pubkey_map<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<HashMap<Pubkey, TokenLimits>>, D::Error> {
+    let Some(limits) = Option::<HashMap<String, TokenLimits>>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+    limits
+        .into_iter()
+        .map(|(key, limits)| {
+            Pubkey::from_str(&key)
+                .map(|mint| (mint, limits))
+                .map_err(|e| serde::de::Error::custom(format!("invalid pubkey {key}: {e}")))
+        })
+        .collect::<Result<_, _>>()
+        .map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::hash::Hash;
+    use solana_sdk::instruction::{AccountMeta, Instruction};
+    use solana_sdk::message::Message;
+    use crate::services::payroll_program::tests::{execute_payroll_data, instruction_data, payroll_run_data};
+    use solana_system_interface::instruction as system_instruction;
+
+    fn message(payer: &Pubkey, instructions: &[Instruction]) -> Vec<u8> {
+        Message::new_with_blockhash(instructions, Some(payer), &Hash::new_unique()).serialize()
+    }
+
+    fn engine(policy: serde_json::Value) -> PolicyEngine {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("policies.json");
+        std::fs::write(&path, serde_json::json!({ "wallets": { "w1": policy } }).to_string()).unwrap();
+        PolicyEngine::load(&path).unwrap()
+    }
+
+    #[tokio::test]
+    async fn enforces_programs_limits_and_destinations() {
+        let (wallet, payee) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let policies = engine(serde_json::json!({
+            "allowed_programs": [solana_system_interface::program::ID.to_string()],
+            "max_lamports_per_transfer": 1_000,
+            "allowed_destinations": [payee.to_string()],
+        }));
+
+        let ok = message(&wallet, &[system_instruction::transfer(&wallet, &payee, 1_000)]);
+        assert!(policies.authorize("w1", &ok).await.is_ok());
+
+        let too_large = message(&wallet, &[system_instruction::transfer(&wallet, &payee, 1_001)]);
+        let stranger = message(&wallet, &[system_instruction::transfer(&wallet, &Pubkey::new_unique(), 1)]);
+        let other_program = message(
+            &wallet,
+            &[Instruction::new_with_bytes(Pubkey::new_unique(), &[], vec![AccountMeta::new(wallet, true)])],
+        );
+        for rejected in [too_large, stranger, other_program, b"raw bytes".to_vec()] {
+            assert!(matches!(policies.authorize("w1", &rejected).await, Err(MpcError::PolicyViolation(_))));
+        }
+
+        // Wallets without a policy are not restricted
+        assert!(policies.authorize("w2", b"raw bytes").await.unwrap().is_none());
+    }
+
+    fn token_instruction(program_id: Pubkey, data: Vec<u8>, mint: &Pubkey, wallet: &Pubkey, payee: &Pubkey) -> Instruction {
+        Instruction::new_with_bytes(
+            program_id,
+            &data,
+            vec![
+                AccountMeta::new(Pubkey::new_unique(), false),
+                AccountMeta::new_readonly(*mint, false),
+                AccountMeta::new(*payee, false),
+                AccountMeta::new_readonly(*wallet, true),
+            ],
+        )
+    }
+
+    fn transfer_checked(amount: u64) -> Vec<u8> {
+        [&[12][..], &amount.to_le_bytes(), &[6]].concat()
+    }
+
+    #[tokio::test]
+    async fn rejects_instructions_that_are_not_transfers() {
+        let (wallet, payee, mint) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let policies = engine(serde_json::json!({ "max_lamports_per_transfer": 1_000 }));
+        let nonce = Pubkey::new_unique();
+
+        let system = [
+            system_instruction::assign(&wallet, &Pubkey::new_unique()),
+            system_instruction::withdraw_nonce_account(&nonce, &wallet, &payee, 1),
+            system_instruction::authorize_nonce_account(&nonce, &wallet, &payee),
+            system_instruction::allocate(&wallet, 10),
+        ];
+        let token = [
+            (SPL_TOKEN_PROGRAM_ID, [&[4][..], &1u64.to_le_bytes()].concat()),
+            (SPL_TOKEN_PROGRAM_ID, [&[13][..], &1u64.to_le_bytes(), &[6]].concat()),
+            (SPL_TOKEN_PROGRAM_ID, vec![6, 0, 0]),
+            (SPL_TOKEN_PROGRAM_ID, vec![9]),
+            (SPL_TOKEN_PROGRAM_ID, [&[8][..], &1u64.to_le_bytes()].concat()),
+            (SPL_TOKEN_2022_PROGRAM_ID, [&[15][..], &1u64.to_le_bytes(), &[6]].concat()),
+            // Unchecked Transfer does not name the mint
+            (SPL_TOKEN_PROGRAM_ID, [&[3][..], &1u64.to_le_bytes()].concat()),
+            // TransferCheckedWithFee is Token-2022 only
+            (SPL_TOKEN_PROGRAM_ID, [&[26, 1][..], &1u64.to_le_bytes(), &[6], &0u64.to_le_bytes()].concat()),
+        ]
+        .map(|(program_id, data)| token_instruction(program_id, data, &mint, &wallet, &payee));
+        for instruction in system.into_iter().chain(token) {
+            let rejected = message(&wallet, &[instruction]);
+            assert!(matches!(policies.authorize("w1", &rejected).await, Err(MpcError::PolicyViolation(_))));
+        }
+
+        let advance = system_instruction::advance_nonce_account(&nonce, &wallet);
+        let checked = token_instruction(SPL_TOKEN_PROGRAM_ID, transfer_checked(5), &mint, &wallet, &payee);
+        let with_fee = token_instruction(
+            SPL_TOKEN_2022_PROGRAM_ID,
+            [&[26, 1][..], &5u64.to_le_bytes(), &[6], &0u64.to_le_bytes()].concat(),
+            &mint,
+            &wallet,
+            &payee,
+        );
+        assert!(policies.authorize("w1", &message(&wallet, &[advance, checked, with_fee])).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn enforces_token_limits_per_mint() {
+        let (wallet, payee) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (usdc, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let policies = engine(serde_json::json!({
+            "max_lamports_per_transfer": 1,
+            "daily_spend_cap_lamports": 1,
+            "token_limits": { usdc.to_string(): { "max_per_transfer": 1_000, "daily_spend_cap": 1_500 } },
+        }));
+        let usdc_transfer = |amount| {
+            message(&wallet, &[token_instruction(SPL_TOKEN_PROGRAM_ID, transfer_checked(amount), &usdc, &wallet, &payee)])
+        };
+
+        // Token amounts count against the mint's limits, not the lamport ones
+        let spend = policies.authorize("w1", &usdc_transfer(1_000)).await.unwrap().unwrap();
+        assert!(matches!(policies.authorize("w1", &usdc_transfer(1_001)).await, Err(MpcError::PolicyViolation(_))));
+        assert!(matches!(policies.authorize("w1", &usdc_transfer(600)).await, Err(MpcError::PolicyViolation(_))));
+        policies.refund(spend);
+        assert!(policies.authorize("w1", &usdc_transfer(600)).await.is_ok());
+
+        let unlisted = token_instruction(SPL_TOKEN_PROGRAM_ID, transfer_checked(1), &other, &wallet, &payee);
+        let unlisted = message(&wallet, &[unlisted]);
+        assert!(matches!(policies.authorize("w1", &unlisted).await, Err(MpcError::PolicyViolation(_))));
+    }
+
+    #[tokio::test]
+    async fn daily_cap_counts_authorized_transfers_until_refunded() {
+        let (wallet, payee) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let policies = engine(serde_json::json!({ "daily_spend_cap_lamports": 1_500 }));
+        let transfer = message(&wallet, &[system_instruction::transfer(&wallet, &payee, 1_000)]);
+
+        let spend = policies.authorize("w1", &transfer).await.unwrap().unwrap();
+        assert!(matches!(policies.authorize("w1", &transfer).await, Err(MpcError::PolicyViolation(_))));
+
+        policies.refund(spend);
+        assert!(policies.authorize("w1", &transfer).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn caps_execute_payroll_at_the_scheduled_total() {
+        let (authority, payroll_run, employee) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let policies = engine(serde_json::json!({ "cap_payroll_at_scheduled_total": true }));
+        let payroll_instruction = |data: Vec<u8>| {
+            Instruction::new_with_bytes(
+                payroll_program::PROGRAM_ID,
+                &data,
+                vec![
+                    AccountMeta::new(Pubkey::new_unique(), false),
+                    AccountMeta::new(payroll_run, false),
+                    AccountMeta::new(authority, true),
+                ],
+            )
+        };
+        let mut schedule_args = 1u64.to_le_bytes().to_vec();
+        schedule_args.extend_from_slice(&3_000u64.to_le_bytes());
+        let schedule = payroll_instruction(instruction_data("schedule_payroll", &schedule_args));
+
+        let within = message(
+            &authority,
+            &[schedule.clone(), payroll_instruction(execute_payroll_data(&[(employee, 1_000), (employee, 2_000)]))],
+        );
+        assert!(policies.authorize("w1", &within).await.is_ok());
+        let described = policies.describe(&within);
+        assert_eq!(described[1]["instruction"], "execute_payroll");
+        assert_eq!(described[1]["total"], 3_000);
+        assert_eq!(described[1]["payroll_run"], payroll_run.to_string());
+
+        let over = message(&authority, &[schedule, payroll_instruction(execute_payroll_data(&[(employee, 3_001)]))]);
+        assert!(matches!(policies.authorize("w1", &over).await, Err(MpcError::PolicyViolation(_))));
+
+        // Without the schedule in the message the run must be looked up on chain
+        let unscheduled = message(&authority, &[payroll_instruction(execute_payroll_data(&[(employee, 1)]))]);
+        assert!(matches!(policies.authorize("w1", &unscheduled).await, Err(MpcError::PolicyViolation(_))));
+
+        let unknown = message(&authority, &[payroll_instruction(instruction_data("drain_escrow", &[]))]);
+        assert!(matches!(policies.authorize("w1", &unknown).await, Err(MpcError::PolicyViolation(_))));
+    }
+
+    /// JSON-RPC stub answering `getAccountInfo` with `account` owned by the
+    /// payroll program.
+    async fn serve_account(account: Vec<u8>) -> String {
+        use actix_web::{web, App, HttpResponse, HttpServer};
+        use base64::{engine::general_purpose, Engine as _};
+
+        let data = general_purpose::STANDARD.encode(account);
+        let server = HttpServer::new(move || {
+            let data = data.clone();
+            App::new().route(
+                "/",
+                web::post().to(move || {
+                    let data = data.clone();
+                    async move {
+                        HttpResponse::Ok().json(serde_json::json!({
+                            "jsonrpc": "2.0",
+                            "id": 1,
+                            "result": { "context": { "slot": 1 }, "value": {
+                                "owner": payroll_program::PROGRAM_ID.to_string(),
+                                "data": [data, "base64"],
+                            } },
+                        }))
+                    }
+                }),
+            )
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let addr = server.addrs()[0];
+        actix_web::rt::spawn(server.run());
+        format!("http://{addr}")
+    }
+
+    #[actix_web::test]
+    async fn caps_execute_payroll_at_what_the_run_has_left() {
+        let (authority, payroll_run, employee) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let url = serve_account(payroll_run_data(&Pubkey::new_unique(), 3_000, 2_000)).await;
+        let policies = PolicyEngine {
+            rpc: Some(SolanaRpc::new(url)),
+            ..engine(serde_json::json!({ "cap_payroll_at_scheduled_total": true }))
+        };
+        let execute = |amount| {
+            let instruction = Instruction::new_with_bytes(
+                payroll_program::PROGRAM_ID,
+                &execute_payroll_data(&[(employee, amount)]),
+                vec![
+                    AccountMeta::new(Pubkey::new_unique(), false),
+                    AccountMeta::new(payroll_run, false),
+                    AccountMeta::new(authority, true),
+                ],
+            );
+            message(&authority, &[instruction])
+        };
+
+        // 2_000 of the 3_000 scheduled were already disbursed
+        assert!(policies.authorize("w1", &execute(1_000)).await.is_ok());
+        assert!(matches!(policies.authorize("w1", &execute(1_001)).await, Err(MpcError::PolicyViolation(_))));
+    }
+
+    #[tokio::test]
+    async fn counts_token_payroll_runs_against_their_mint() {
+        let (authority, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let policies = engine(serde_json::json!({
+            "max_lamports_per_transfer": 1,
+            "token_limits": { mint.to_string(): { "max_per_transfer": 5_000 } },
+        }));
+        let schedule = |total_amount: u64, payment_mint: Pubkey| {
+            let mut args = 1u64.to_le_bytes().to_vec();
+            args.extend_from_slice(&total_amount.to_le_bytes());
+            let mut accounts: Vec<AccountMeta> =
+                (0..5).map(|_| AccountMeta::new(Pubkey::new_unique(), false)).collect();
+            accounts[3] = AccountMeta::new(authority, true);
+            accounts.push(AccountMeta::new_readonly(payment_mint, false));
+            let data = instruction_data("schedule_payroll", &args);
+            message(&authority, &[Instruction::new_with_bytes(payroll_program::PROGRAM_ID, &data, accounts)])
+        };
+
+        assert!(policies.authorize("w1", &schedule(5_000, mint)).await.is_ok());
+        assert!(matches!(policies.authorize("w1", &schedule(5_001, mint)).await, Err(MpcError::PolicyViolation(_))));
+        // Without a mint the run is funded in lamports
+        assert!(matches!(
+            policies.authorize("w1", &schedule(2, payroll_program::PROGRAM_ID)).await,
+            Err(MpcError::PolicyViolation(_))
+        ));
+    }
+}
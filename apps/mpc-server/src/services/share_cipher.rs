@@ -0,0 +1,104 @@
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose, Engine as _};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rand::RngCore;
+use std::fs;
+use std::path::Path;
+
+const NONCE_LEN: usize = 12;
+
+/// Seals key share material with ChaCha20-Poly1305 under the server master
+/// key. The associated data binds a ciphertext to its wallet and share id, so
+/// sealed shares cannot be swapped between records.
+pub struct ShareCipher {
+    cipher: ChaCha20Poly1305,
+}
+
+impl ShareCipher {
+    pub fn new(master_key: [u8; 32]) -> Self {
+        Self {
+            cipher: ChaCha20Poly1305::new(Key::from_slice(&master_key)),
+        }
+    }
+
+    /// Loads the master key from `encoded_key` (base64) when set, otherwise
+    /// from `key_file`, generating the file on first start.
+    pub fn from_config(encoded_key: Option<&str>, key_file: &str) -> Result<Self> {
+        if let Some(encoded) = encoded_key {
+            return Ok(Self::new(decode_master_key(encoded.trim())?));
+        }
+
+        let path = Path::new(key_file);
+        if path.exists() {
+            let encoded = fs::read_to_string(path)
+                .with_context(|| format!("reading master key file {key_file}"))?;
+            return Ok(Self::new(decode_master_key(encoded.trim())?));
+        }
+
+        let mut master_key = [0u8; 32];
+        rand::rngs::OsRng.fill_bytes(&mut master_key);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        write_private(path, general_purpose::STANDARD.encode(master_key).as_bytes())
+            .with_context(|| format!("writing master key file {key_file}"))?;
+        log::warn!("Generated new share master key at {key_file}");
+
+        Ok(Self::new(master_key))
+    }
+
+    pub fn seal(&self, plaintext: &[u8], associated_data: &[u8]) -> Result<Vec<u8>> {
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::rngs::OsRng.fill_bytes(&mut nonce);
+
+        let ciphertext = self
+            .cipher
+            .encrypt(Nonce::from_slice(&nonce), Payload { msg: plaintext, aad: associated_data })
+            .map_err(|_| anyhow!("failed to seal key share"))?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    pub fn open(&self, sealed: &[u8], associated_data: &[u8]) -> Result<Vec<u8>> {
+        if sealed.len() < NONCE_LEN {
+            return Err(anyhow!("sealed key share is truncated"));
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+
+        self.cipher
+            .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: associated_data })
+            .map_err(|_| anyhow!("failed to open key share: wrong master key or tampered record"))
+    }
+}
+
+fn decode_master_key(encoded: &str) -> Result<[u8; 32]> {
+    general_purpose::STANDARD
+        .decode(encoded)
+        .context("master key is not valid base64")?
+        .try_into()
+        .map_err(|_| anyhow!("master key must be 32 bytes"))
+}
+
+/// Writes a file readable only by the server user.
+pub fn write_private(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::io::Write;
+        use std::os::unix::fs::OpenOptionsExt;
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(path)?;
+        file.write_all(contents)?;
+        file.sync_all()
+    }
+    #[cfg(not(unix))]
+    {
+        fs::write(path, contents)
+    }
+}
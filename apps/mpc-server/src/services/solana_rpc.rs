@@ -0,0 +1,96 @@
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose, Engine as _};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+
+/// Minimal async JSON-RPC client for the Solana cluster calls the MPC server
+/// needs.
+#[derive(Clone)]
+pub struct SolanaRpc {
+    client: reqwest::Client,
+    url: String,
+}
+
+#[derive(Deserialize)]
+struct RpcResponse<T> {
+    result: Option<T>,
+    error: Option<Value>,
+}
+
+#[derive(Deserialize)]
+struct RpcContextValue<T> {
+    value: T,
+}
+
+#[derive(Deserialize)]
+struct RpcAccount {
+    owner: String,
+    data: (String, String),
+}
+
+/// Owner and raw data of an on-chain account.
+pub struct AccountData {
+    pub owner: Pubkey,
+    pub data: Vec<u8>,
+}
+
+impl SolanaRpc {
+    pub fn new(url: String) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url,
+        }
+    }
+
+    pub async fn get_balance(&self, address: &Pubkey) -> Result<u64> {
+        let response: RpcContextValue<u64> = self
+            .call("getBalance", json!([address.to_string()]))
+            .await?;
+        Ok(response.value)
+    }
+
+    /// Account owner and data, or `None` if the account does not exist.
+    pub async fn get_account(&self, address: &Pubkey) -> Result<Option<AccountData>> {
+        let response: RpcContextValue<Option<RpcAccount>> = self
+            .call("getAccountInfo", json!([address.to_string(), { "encoding": "base64" }]))
+            .await?;
+        let Some(account) = response.value else {
+            return Ok(None);
+        };
+        Ok(Some(AccountData {
+            owner: account.owner.parse()?,
+            data: general_purpose::STANDARD.decode(&account.data.0)?,
+        }))
+    }
+
+    pub async fn request_airdrop(&self, address: &Pubkey, lamports: u64) -> Result<Signature> {
+        let signature: String = self
+            .call("requestAirdrop", json!([address.to_string(), lamports]))
+            .await?;
+        Ok(signature.parse()?)
+    }
+
+    async fn call<T: for<'de> Deserialize<'de>>(&self, method: &str, params: Value) -> Result<T> {
+        let response: RpcResponse<T> = self
+            .client
+            .post(&self.url)
+            .json(&json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": method,
+                "params": params,
+            }))
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        match (response.result, response.error) {
+            (Some(result), _) => Ok(result),
+            (None, Some(error)) => Err(anyhow!("RPC {method} failed: {error}")),
+            (None, None) => Err(anyhow!("RPC {method} returned no result")),
+        }
+    }
+}
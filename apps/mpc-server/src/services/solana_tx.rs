@@ -0,0 +1,107 @@
+use base64::{engine::general_purpose, Engine as _};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::VersionedTransaction;
+
+use crate::errors::MpcError;
+
+/// Decodes a base64, bincode-serialized `Transaction` or `VersionedTransaction`.
+/// Legacy transactions share the versioned wire format, so both parse here.
+pub fn decode_transaction(encoded: &str) -> Result<VersionedTransaction, MpcError> {
+    let bytes = general_purpose::STANDARD
+        .decode(encoded)
+        .map_err(|e| MpcError::InvalidRequest(format!("Invalid base64 transaction: {e}")))?;
+    let transaction: VersionedTransaction = bincode::deserialize(&bytes)
+        .map_err(|e| MpcError::InvalidRequest(format!("Invalid Solana transaction: {e}")))?;
+
+    transaction
+        .message
+        .sanitize()
+        .map_err(|e| MpcError::InvalidRequest(format!("Malformed transaction message: {e}")))?;
+    Ok(transaction)
+}
+
+pub fn encode_transaction(transaction: &VersionedTransaction) -> Result<String, MpcError> {
+    let bytes = bincode::serialize(transaction)
+        .map_err(|e| MpcError::InternalError(format!("Failed to serialize transaction: {e}")))?;
+    Ok(general_purpose::STANDARD.encode(bytes))
+}
+
+/// Position of `signer` among the message's required signers.
+pub fn signer_index(transaction: &VersionedTransaction, signer: &Pubkey) -> Result<usize, MpcError> {
+    let required = transaction.message.header().num_required_signatures as usize;
+    transaction
+        .message
+        .static_account_keys()
+        .iter()
+        .take(required)
+        .position(|key| key == signer)
+        .ok_or_else(|| MpcError::NotARequiredSigner(signer.to_string()))
+}
+
+/// Places `signature` in the signer's slot, allocating empty slots for
+/// transactions submitted without signatures.
+pub fn apply_signature(transaction: &mut VersionedTransaction, index: usize, signature: Signature) {
+    let required = transaction.message.header().num_required_signatures as usize;
+    transaction.signatures.resize(required, Signature::default());
+    transaction.signatures[index] = signature;
+}
+
+/// Required signatures still missing or invalid.
+pub fn missing_signatures(transaction: &VersionedTransaction) -> usize {
+    transaction
+        .verify_with_results()
+        .into_iter()
+        .filter(|valid| !valid)
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::mpc_engine::tests::engine;
+    use solana_sdk::hash::Hash;
+    use solana_sdk::message::Message;
+    use solana_sdk::instruction::{AccountMeta, Instruction};
+    use solana_sdk::transaction::Transaction;
+
+    #[tokio::test]
+    async fn signs_legacy_transaction_for_required_signer() {
+        let engine = engine();
+        let wallet = engine.generate_key(2, 3, None, None).await.unwrap();
+        let wallet_pubkey = Pubkey::new_from_array(wallet.public_key.to_bytes());
+
+        let instruction = Instruction::new_with_bytes(
+            Pubkey::new_unique(),
+            &[1, 2, 3],
+            vec![AccountMeta::new(wallet_pubkey, true), AccountMeta::new(Pubkey::new_unique(), false)],
+        );
+        let message = Message::new_with_blockhash(&[instruction], Some(&wallet_pubkey), &Hash::new_unique());
+        let unsigned = general_purpose::STANDARD.encode(bincode::serialize(&Transaction::new_unsigned(message)).unwrap());
+
+        let mut transaction = decode_transaction(&unsigned).unwrap();
+        let index = signer_index(&transaction, &wallet_pubkey).unwrap();
+        assert_eq!(missing_signatures(&transaction), 1);
+        assert!(matches!(
+            signer_index(&transaction, &Pubkey::new_unique()),
+            Err(MpcError::NotARequiredSigner(_))
+        ));
+
+        let share_ids = wallet.shares[..2].iter().map(|s| s.share_id.clone()).collect();
+        let signature = engine
+            .sign_message(&wallet.wallet_id, &transaction.message.serialize(), share_ids)
+            .await
+            .unwrap();
+        apply_signature(&mut transaction, index, Signature::from(signature.to_bytes()));
+
+        let signed = decode_transaction(&encode_transaction(&transaction).unwrap()).unwrap();
+        assert_eq!(missing_signatures(&signed), 0);
+        assert!(signed.into_legacy_transaction().unwrap().verify().is_ok());
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        assert!(decode_transaction("not base64!").is_err());
+        assert!(decode_transaction(&general_purpose::STANDARD.encode([1, 2, 3])).is_err());
+    }
+}
@@ -0,0 +1,133 @@
+use actix_tls::accept::rustls_0_23::TlsStream;
+use actix_web::dev::Extensions;
+use actix_web::rt::net::TcpStream;
+use anyhow::{anyhow, Context, Result};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{RootCertStore, ServerConfig};
+use std::any::Any;
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::Arc;
+
+/// Caller identity taken from a verified client certificate. Available to
+/// handlers and middleware through `req.conn_data::<ClientIdentity>()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientIdentity {
+    pub subject: String,
+    pub common_name: Option<String>,
+}
+
+/// rustls server configuration that requires a client certificate signed by
+/// one of the CAs in `client_ca_file`.
+pub fn server_config(cert_file: &str, key_file: &str, client_ca_file: &str) -> Result<ServerConfig> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+
+    let mut client_roots = RootCertStore::empty();
+    for ca in load_certs(client_ca_file)? {
+        client_roots
+            .add(ca)
+            .with_context(|| format!("invalid client CA certificate in {client_ca_file}"))?;
+    }
+    let client_verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(client_roots), provider.clone())
+        .build()
+        .context("building client certificate verifier")?;
+
+    ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()?
+        .with_client_cert_verifier(client_verifier)
+        .with_single_cert(load_certs(cert_file)?, load_private_key(key_file)?)
+        .context("loading server certificate")
+}
+
+/// `HttpServer::on_connect` hook recording the client certificate identity of
+/// TLS connections.
+pub fn on_connect(connection: &dyn Any, data: &mut Extensions) {
+    let Some(tls) = connection.downcast_ref::<TlsStream<TcpStream>>() else {
+        return;
+    };
+    let (_, session) = tls.get_ref();
+    let identity = session
+        .peer_certificates()
+        .and_then(|chain| chain.first())
+        .and_then(|cert| client_identity(cert.as_ref()));
+
+    if let Some(identity) = identity {
+        data.insert(identity);
+    }
+}
+
+fn client_identity(der: &[u8]) -> Option<ClientIdentity> {
+    let (_, cert) = x509_parser::parse_x509_certificate(der).ok()?;
+    let subject = cert.subject();
+    let common_name = subject
+        .iter_common_name()
+        .next()
+        .and_then(|cn| cn.as_str().ok())
+        .map(str::to_string);
+
+    Some(ClientIdentity {
+        subject: subject.to_string(),
+        common_name,
+    })
+}
+
+fn load_certs(path: &str) -> Result<Vec<CertificateDer<'static>>> {
+    let mut reader = BufReader::new(File::open(path).with_context(|| format!("opening {path}"))?);
+    let certs = rustls_pemfile::certs(&mut reader)
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("reading certificates from {path}"))?;
+    if certs.is_empty() {
+        return Err(anyhow!("no certificates found in {path}"));
+    }
+    Ok(certs)
+}
+
+fn load_private_key(path: &str) -> Result<PrivateKeyDer<'static>> {
+    let mut reader = BufReader::new(File::open(path).with_context(|| format!("opening {path}"))?);
+    rustls_pemfile::private_key(&mut reader)
+        .with_context(|| format!("reading private key from {path}"))?
+        .ok_or_else(|| anyhow!("no private key found in {path}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rcgen::{BasicConstraints, CertificateParams, DnType, IsCa, KeyPair};
+
+    #[test]
+    fn builds_mtls_config_and_maps_client_subject() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = |name: &str| dir.path().join(name).to_str().unwrap().to_string();
+
+        let ca_key = KeyPair::generate().unwrap();
+        let mut ca_params = CertificateParams::new(Vec::new()).unwrap();
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        ca_params.distinguished_name.push(DnType::CommonName, "PayrollX Internal CA");
+        let ca = ca_params.self_signed(&ca_key).unwrap();
+
+        let server_key = KeyPair::generate().unwrap();
+        let server = CertificateParams::new(vec!["mpc-server".to_string()])
+            .unwrap()
+            .signed_by(&server_key, &ca, &ca_key)
+            .unwrap();
+
+        let client_key = KeyPair::generate().unwrap();
+        let mut client_params = CertificateParams::new(Vec::new()).unwrap();
+        client_params.distinguished_name.push(DnType::CommonName, "payroll-service");
+        client_params.distinguished_name.push(DnType::OrganizationName, "PayrollX");
+        let client = client_params.signed_by(&client_key, &ca, &ca_key).unwrap();
+
+        std::fs::write(path("ca.pem"), ca.pem()).unwrap();
+        std::fs::write(path("server.pem"), server.pem()).unwrap();
+        std::fs::write(path("server.key"), server_key.serialize_pem()).unwrap();
+
+        assert!(server_config(&path("server.pem"), &path("server.key"), &path("ca.pem")).is_ok());
+        assert!(server_config(&path("server.pem"), &path("server.key"), &path("missing.pem")).is_err());
+        assert!(server_config(&path("server.pem"), &path("server.key"), &path("server.key")).is_err());
+
+        let identity = client_identity(client.der()).unwrap();
+        assert_eq!(identity.common_name.as_deref(), Some("payroll-service"));
+        assert!(identity.subject.contains("CN=payroll-service"), "{}", identity.subject);
+    }
+}
@@ -1,5 +1,18 @@
+// The IDL instructions `#[program]` generates still call the deprecated
+// `AccountInfo::realloc`.
+#![allow(deprecated)]
+
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Token, Transfer};
+use anchor_lang::system_program;
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+use anchor_spl::token_interface::spl_token_2022::extension::transfer_fee::TransferFeeConfig;
+use anchor_spl::token_interface::spl_token_2022::extension::{
+    BaseStateWithExtensions, ExtensionType, StateWithExtensions,
+};
+use anchor_spl::token_interface::{
+    self, spl_token_2022, CloseAccount, HarvestWithheldTokensToMint, Mint, TokenAccount,
+    TokenInterface, TransferChecked, TransferCheckedWithFee,
+};
 
 declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
 
@@ -69,6 +82,29 @@ pub mod payroll_solana {
         Ok(())
     }
 
+    /// Deactivate an employee; inactive employees can no longer be paid
+    pub fn deactivate_employee(ctx: Context<DeactivateEmployee>) -> Result<()> {
+        let organization = &ctx.accounts.organization;
+        let employee = &mut ctx.accounts.employee;
+
+        // Check authority
+        require!(
+            ctx.accounts.authority.key() == organization.authority,
+            ErrorCode::InvalidAuthority
+        );
+        require!(employee.is_active, ErrorCode::EmployeeNotActive);
+
+        employee.is_active = false;
+
+        emit!(EmployeeDeactivated {
+            organization: organization.key(),
+            employee: employee.key(),
+            wallet: employee.wallet,
+        });
+
+        Ok(())
+    }
+
     /// Schedule a payroll run
     pub fn schedule_payroll(
         ctx: Context<SchedulePayroll>,
@@ -92,88 +128,261 @@ pub mod payroll_solana {
         payroll_run.escrow_account = ctx.accounts.escrow_account.key();
         payroll_run.created_at = Clock::get()?.unix_timestamp;
         payroll_run.bump = ctx.bumps.payroll_run;
+        payroll_run.escrow_bump = ctx.bumps.escrow_account;
 
-        // Transfer funds from authority to escrow
-        let transfer_instruction = anchor_lang::solana_program::system_instruction::transfer(
-            &ctx.accounts.authority.key(),
-            &ctx.accounts.escrow_account.key(),
-            total_amount,
-        );
+        // Fund the escrow: tokens into the run's escrow token account when a
+        // mint is given, SOL into the escrow PDA otherwise
+        match (
+            &ctx.accounts.payment_mint,
+            &ctx.accounts.escrow_token_account,
+            &ctx.accounts.authority_token_account,
+            &ctx.accounts.token_program,
+        ) {
+            (
+                Some(payment_mint),
+                Some(escrow_token_account),
+                Some(authority_token_account),
+                Some(token_program),
+            ) => {
+                check_mint_extensions(payment_mint)?;
 
-        anchor_lang::solana_program::program::invoke(
-            &transfer_instruction,
-            &[
-                ctx.accounts.authority.to_account_info(),
-                ctx.accounts.escrow_account.to_account_info(),
-                ctx.accounts.system_program.to_account_info(),
-            ],
-        )?;
+                // Transfer-fee mints withhold part of the deposit; send enough
+                // for the escrow to end up with the full total
+                let deposit = match transfer_fee_config(payment_mint)? {
+                    Some(config) => config
+                        .get_epoch_fee(Clock::get()?.epoch)
+                        .calculate_pre_fee_amount(total_amount)
+                        .ok_or(ErrorCode::Overflow)?,
+                    None => total_amount,
+                };
+                payroll_run.fees_withheld = transfer_tokens(
+                    token_program,
+                    authority_token_account,
+                    payment_mint,
+                    escrow_token_account.to_account_info(),
+                    ctx.accounts.authority.to_account_info(),
+                    &[],
+                    deposit,
+                )?;
+                payroll_run.payment_mint = payment_mint.key();
+            }
+            (None, None, None, _) => {
+                // A System transfer may not leave the escrow with less than
+                // the rent-exempt minimum unless it empties it. Fund that
+                // minimum on top of the total so every batch can pay out any
+                // part of what is left; closing the run returns it
+                let rent_reserve = Rent::get()?
+                    .minimum_balance(0)
+                    .saturating_sub(ctx.accounts.escrow_account.lamports());
+                let deposit = total_amount
+                    .checked_add(rent_reserve)
+                    .ok_or(ErrorCode::Overflow)?;
+                let transfer_instruction =
+                    anchor_lang::solana_program::system_instruction::transfer(
+                        &ctx.accounts.authority.key(),
+                        &ctx.accounts.escrow_account.key(),
+                        deposit,
+                    );
+
+                anchor_lang::solana_program::program::invoke(
+                    &transfer_instruction,
+                    &[
+                        ctx.accounts.authority.to_account_info(),
+                        ctx.accounts.escrow_account.to_account_info(),
+                        ctx.accounts.system_program.to_account_info(),
+                    ],
+                )?;
+                payroll_run.payment_mint = system_program::ID;
+            }
+            _ => return err!(ErrorCode::MissingTokenAccounts),
+        }
 
         emit!(PayrollScheduled {
             organization: organization.key(),
             payroll_run: payroll_run.key(),
             run_id,
             total_amount,
+            payment_mint: payroll_run.payment_mint,
         });
 
         Ok(())
     }
 
-    /// Execute payroll payments
-    pub fn execute_payroll(
-        ctx: Context<ExecutePayroll>,
+    /// Execute payroll payments. A run can be paid out over several batches;
+    /// it completes once its whole total has been disbursed.
+    pub fn execute_payroll<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ExecutePayroll<'info>>,
         employees: Vec<EmployeePayment>,
     ) -> Result<()> {
-        // Validate state inline to avoid lifetime issues
         require!(
             ctx.accounts.authority.key() == ctx.accounts.organization.authority,
             ErrorCode::InvalidAuthority
         );
 
         require!(
-            ctx.accounts.payroll_run.status == PayrollStatus::Pending,
+            matches!(
+                ctx.accounts.payroll_run.status,
+                PayrollStatus::Pending | PayrollStatus::InProgress
+            ),
             ErrorCode::InvalidPayrollStatus
         );
-        
+
+        // Refuse the batch up front if it would pay out more than the run
+        // escrowed
+        let batch_total = employees.iter().try_fold(0u64, |total, payment| {
+            total.checked_add(payment.amount).ok_or(ErrorCode::Overflow)
+        })?;
+        let run_disbursed = ctx
+            .accounts
+            .payroll_run
+            .total_disbursed
+            .checked_add(batch_total)
+            .ok_or(ErrorCode::Overflow)?;
+        require!(
+            run_disbursed <= ctx.accounts.payroll_run.total_amount,
+            ErrorCode::RunTotalExceeded
+        );
+
+        let organization_key = ctx.accounts.organization.key();
+        let payment_mint = ctx.accounts.payroll_run.payment_mint;
+        let payroll_run_key = ctx.accounts.payroll_run.key();
+        let escrow_bump = [ctx.accounts.payroll_run.escrow_bump];
+        let escrow_seeds: &[&[u8]] = &[b"escrow", payroll_run_key.as_ref(), &escrow_bump];
+
+        let escrow_tokens = if payment_mint == system_program::ID {
+            None
+        } else {
+            match (
+                &ctx.accounts.payment_mint,
+                &ctx.accounts.escrow_token_account,
+            ) {
+                (Some(mint), Some(escrow_token_account)) => Some((mint, escrow_token_account)),
+                _ => return err!(ErrorCode::MissingTokenAccounts),
+            }
+        };
+
         let mut total_disbursed = 0u64;
+        let mut fees_withheld = 0u64;
 
         for employee_payment in employees.iter() {
-            // Transfer funds - SOL only for now to avoid lifetime issues
-            require!(
-                employee_payment.payment_token == anchor_lang::solana_program::system_program::ID,
-                ErrorCode::InsufficientFunds
+            require_keys_eq!(
+                employee_payment.payment_token,
+                payment_mint,
+                ErrorCode::PaymentTokenMismatch
             );
-            
-            // SOL transfer - find wallet account in remaining accounts  
-            let wallet_account_info = ctx
-                .remaining_accounts
-                .iter()
-                .find(|acc| acc.key() == employee_payment.wallet)
-                .ok_or(ErrorCode::InsufficientFunds)?;
 
-            let transfer_instruction = anchor_lang::solana_program::system_instruction::transfer(
-                &ctx.accounts.escrow_account.key(),
-                &employee_payment.wallet,
-                employee_payment.amount,
-            );
+            let employee_info =
+                remaining_account(ctx.remaining_accounts, &employee_payment.employee)
+                    .ok_or(ErrorCode::EmployeeNotFound)?;
+            validate_payment(&organization_key, employee_payment, employee_info)?;
 
-            anchor_lang::solana_program::program::invoke(
-                &transfer_instruction,
+            let (receipt_key, receipt_bump) = Pubkey::find_program_address(
                 &[
-                    ctx.accounts.escrow_account.to_account_info(),
-                    wallet_account_info.clone(),
-                    ctx.accounts.system_program.to_account_info(),
+                    b"receipt",
+                    payroll_run_key.as_ref(),
+                    employee_payment.employee.as_ref(),
                 ],
-            )?;
+                &crate::ID,
+            );
+            let receipt_info = remaining_account(ctx.remaining_accounts, &receipt_key)
+                .ok_or(ErrorCode::ReceiptAccountMissing)?;
+
+            let fee = if let Some((mint, escrow_token_account)) = escrow_tokens {
+                // Tokens only ever go to the employee's associated token account
+                let destination = get_associated_token_address_with_program_id(
+                    &employee_payment.wallet,
+                    &payment_mint,
+                    &ctx.accounts.token_program.key(),
+                );
+                if let Some(token_account) = employee_payment.token_account {
+                    require_keys_eq!(token_account, destination, ErrorCode::InvalidTokenAccount);
+                }
+                let destination_info = remaining_account(ctx.remaining_accounts, &destination)
+                    .ok_or(ErrorCode::PaymentAccountMissing)?;
+
+                transfer_tokens(
+                    &ctx.accounts.token_program,
+                    escrow_token_account,
+                    mint,
+                    destination_info.clone(),
+                    ctx.accounts.escrow_account.to_account_info(),
+                    &[escrow_seeds],
+                    employee_payment.amount,
+                )?
+            } else {
+                let wallet_account_info =
+                    remaining_account(ctx.remaining_accounts, &employee_payment.wallet)
+                        .ok_or(ErrorCode::PaymentAccountMissing)?;
+
+                let transfer_instruction =
+                    anchor_lang::solana_program::system_instruction::transfer(
+                        &ctx.accounts.escrow_account.key(),
+                        &employee_payment.wallet,
+                        employee_payment.amount,
+                    );
+
+                anchor_lang::solana_program::program::invoke_signed(
+                    &transfer_instruction,
+                    &[
+                        ctx.accounts.escrow_account.to_account_info(),
+                        wallet_account_info.clone(),
+                        ctx.accounts.system_program.to_account_info(),
+                    ],
+                    &[escrow_seeds],
+                )?;
+                0
+            };
 
             total_disbursed = total_disbursed
                 .checked_add(employee_payment.amount)
                 .ok_or(ErrorCode::Overflow)?;
+            fees_withheld = fees_withheld.checked_add(fee).ok_or(ErrorCode::Overflow)?;
+
+            create_receipt(
+                receipt_info,
+                &ctx.accounts.authority,
+                &ctx.accounts.system_program,
+                &[
+                    b"receipt",
+                    payroll_run_key.as_ref(),
+                    employee_payment.employee.as_ref(),
+                    &[receipt_bump],
+                ],
+                &PaymentReceipt {
+                    payroll_run: payroll_run_key,
+                    employee: employee_payment.employee,
+                    amount: employee_payment.amount,
+                    fee,
+                    paid_at: Clock::get()?.unix_timestamp,
+                    bump: receipt_bump,
+                },
+            )?;
+
+            emit!(EmployeePaid {
+                payroll_run: payroll_run_key,
+                employee: employee_payment.employee,
+                wallet: employee_payment.wallet,
+                amount: employee_payment.amount,
+                fee,
+            });
         }
 
         // NOW mutate after loop completes
         let payroll_run = &mut ctx.accounts.payroll_run;
-        payroll_run.status = PayrollStatus::Completed;
+        payroll_run.total_disbursed = run_disbursed;
+        payroll_run.total_employees = u16::try_from(employees.len())
+            .ok()
+            .and_then(|count| payroll_run.total_employees.checked_add(count))
+            .ok_or(ErrorCode::Overflow)?;
+        payroll_run.status = if run_disbursed == payroll_run.total_amount {
+            PayrollStatus::Completed
+        } else {
+            PayrollStatus::InProgress
+        };
+        payroll_run.fees_withheld = payroll_run
+            .fees_withheld
+            .checked_add(fees_withheld)
+            .ok_or(ErrorCode::Overflow)?;
 
         let organization = &mut ctx.accounts.organization;
         organization.total_disbursed = organization
@@ -185,13 +394,15 @@ pub mod payroll_solana {
             organization: organization.key(),
             payroll_run: payroll_run.key(),
             total_disbursed,
+            fees_withheld,
             employee_count: employees.len() as u16,
         });
 
         Ok(())
     }
 
-    /// Close a completed payroll run
+    /// Close a payroll run that is not yet closed, returning what is left in
+    /// escrow. A pending run is cancelled with its whole escrow returned
     pub fn close_payroll_run(ctx: Context<ClosePayrollRun>) -> Result<()> {
         let payroll_run = &mut ctx.accounts.payroll_run;
         let organization = &mut ctx.accounts.organization;
@@ -203,17 +414,95 @@ pub mod payroll_solana {
         );
 
         require!(
-            payroll_run.status == PayrollStatus::Completed,
+            matches!(
+                payroll_run.status,
+                PayrollStatus::Pending | PayrollStatus::InProgress | PayrollStatus::Completed
+            ),
             ErrorCode::InvalidPayrollStatus
         );
 
-        // Return remaining funds to organization authority
+        let payroll_run_key = payroll_run.key();
+        let escrow_bump = [payroll_run.escrow_bump];
+        let escrow_seeds: &[&[u8]] = &[b"escrow", payroll_run_key.as_ref(), &escrow_bump];
+
+        // Return remaining tokens to the authority and reclaim the escrow
+        // token account's rent
+        let mut returned_amount = 0;
+        if payroll_run.payment_mint != system_program::ID {
+            let (
+                Some(mint),
+                Some(escrow_token_account),
+                Some(authority_token_account),
+                Some(token_program),
+            ) = (
+                &ctx.accounts.payment_mint,
+                &ctx.accounts.escrow_token_account,
+                &ctx.accounts.authority_token_account,
+                &ctx.accounts.token_program,
+            )
+            else {
+                return err!(ErrorCode::MissingTokenAccounts);
+            };
+
+            returned_amount = escrow_token_account.amount;
+            if returned_amount > 0 {
+                let fee = transfer_tokens(
+                    token_program,
+                    escrow_token_account,
+                    mint,
+                    authority_token_account.to_account_info(),
+                    ctx.accounts.escrow_account.to_account_info(),
+                    &[escrow_seeds],
+                    returned_amount,
+                )?;
+                payroll_run.fees_withheld = payroll_run
+                    .fees_withheld
+                    .checked_add(fee)
+                    .ok_or(ErrorCode::Overflow)?;
+            }
+            // Token-2022 only closes accounts without withheld transfer fees
+            if transfer_fee_config(mint)?.is_some() {
+                token_interface::harvest_withheld_tokens_to_mint(
+                    CpiContext::new(
+                        token_program.to_account_info(),
+                        HarvestWithheldTokensToMint {
+                            token_program_id: token_program.to_account_info(),
+                            mint: mint.to_account_info(),
+                        },
+                    ),
+                    vec![escrow_token_account.to_account_info()],
+                )?;
+            }
+            token_interface::close_account(CpiContext::new_with_signer(
+                token_program.to_account_info(),
+                CloseAccount {
+                    account: escrow_token_account.to_account_info(),
+                    destination: ctx.accounts.authority.to_account_info(),
+                    authority: ctx.accounts.escrow_account.to_account_info(),
+                },
+                &[escrow_seeds],
+            ))?;
+        }
+
+        // Return remaining funds to organization authority. The escrow is
+        // owned by the system program, so only a transfer it signs can debit it
         let escrow_balance = ctx.accounts.escrow_account.lamports();
         if escrow_balance > 0 {
-            **ctx.accounts.escrow_account.to_account_info().try_borrow_mut_lamports()? -=
-                escrow_balance;
-            **ctx.accounts.authority.to_account_info().try_borrow_mut_lamports()? +=
-                escrow_balance;
+            let transfer_instruction = anchor_lang::solana_program::system_instruction::transfer(
+                &ctx.accounts.escrow_account.key(),
+                &ctx.accounts.authority.key(),
+                escrow_balance,
+            );
+
+            anchor_lang::solana_program::program::invoke_signed(
+                &transfer_instruction,
+                &[
+                    ctx.accounts.escrow_account.to_account_info(),
+                    ctx.accounts.authority.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+                &[escrow_seeds],
+            )?;
         }
 
         payroll_run.status = PayrollStatus::Cancelled;
@@ -221,7 +510,11 @@ pub mod payroll_solana {
         emit!(PayrollClosed {
             organization: organization.key(),
             payroll_run: payroll_run.key(),
-            returned_amount: escrow_balance,
+            returned_amount: if payroll_run.payment_mint == system_program::ID {
+                escrow_balance
+            } else {
+                returned_amount
+            },
         });
 
         Ok(())
@@ -268,6 +561,22 @@ pub struct AddEmployee<'info> {
     pub system_program: Program<'info, System>,
 }
 
+#[derive(Accounts)]
+pub struct DeactivateEmployee<'info> {
+    #[account(
+        seeds = [b"organization", organization.authority.as_ref()],
+        bump = organization.bump
+    )]
+    pub organization: Account<'info, OrganizationAccount>,
+    #[account(
+        mut,
+        seeds = [b"employee", organization.key().as_ref(), employee.wallet.as_ref()],
+        bump = employee.bump
+    )]
+    pub employee: Account<'info, EmployeeAccount>,
+    pub authority: Signer<'info>,
+}
+
 #[derive(Accounts)]
 #[instruction(run_id: u64, total_amount: u64)]
 pub struct SchedulePayroll<'info> {
@@ -280,7 +589,7 @@ pub struct SchedulePayroll<'info> {
     #[account(
         init,
         payer = authority,
-        space = 8 + 32 + 8 + 2 + 8 + 1 + 32 + 8 + 1,
+        space = 8 + 32 + 8 + 2 + 8 + 1 + 32 + 8 + 1 + 32 + 8 + 1 + 8,
         seeds = [b"payroll_run", organization.key().as_ref(), run_id.to_le_bytes().as_ref()],
         bump
     )]
@@ -294,6 +603,27 @@ pub struct SchedulePayroll<'info> {
     #[account(mut)]
     pub authority: Signer<'info>,
     pub system_program: Program<'info, System>,
+    /// Mint the run pays out in, SPL Token or Token-2022; omit for SOL runs
+    #[account(mint::token_program = token_program)]
+    pub payment_mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"escrow_token", payroll_run.key().as_ref()],
+        bump,
+        token::mint = payment_mint,
+        token::authority = escrow_account,
+        token::token_program = token_program
+    )]
+    pub escrow_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        token::mint = payment_mint,
+        token::authority = authority,
+        token::token_program = token_program
+    )]
+    pub authority_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
 }
 
 #[derive(Accounts)]
@@ -313,15 +643,28 @@ pub struct ExecutePayroll<'info> {
     #[account(
         mut,
         seeds = [b"escrow", payroll_run.key().as_ref()],
-        bump
+        bump = payroll_run.escrow_bump
     )]
     pub escrow_account: SystemAccount<'info>,
-    #[account(mut)]
-    /// CHECK: Optional escrow token account for SPL token payments
-    pub escrow_token_account: Option<UncheckedAccount<'info>>,
+    /// Mint of token runs
+    #[account(
+        address = payroll_run.payment_mint,
+        mint::token_program = token_program
+    )]
+    pub payment_mint: Option<InterfaceAccount<'info, Mint>>,
+    /// Escrow token account of token runs
+    #[account(
+        mut,
+        seeds = [b"escrow_token", payroll_run.key().as_ref()],
+        bump,
+        token::mint = payroll_run.payment_mint,
+        token::authority = escrow_account,
+        token::token_program = token_program
+    )]
+    pub escrow_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
     #[account(mut)]
     pub authority: Signer<'info>,
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
 }
 
@@ -342,12 +685,38 @@ pub struct ClosePayrollRun<'info> {
     #[account(
         mut,
         seeds = [b"escrow", payroll_run.key().as_ref()],
-        bump
+        bump = payroll_run.escrow_bump
     )]
     pub escrow_account: SystemAccount<'info>,
     #[account(mut)]
     pub authority: Signer<'info>,
     pub system_program: Program<'info, System>,
+    /// Mint of token runs
+    #[account(
+        mut,
+        address = payroll_run.payment_mint,
+        mint::token_program = token_program
+    )]
+    pub payment_mint: Option<InterfaceAccount<'info, Mint>>,
+    /// Escrow token account of token runs, closed along with the run
+    #[account(
+        mut,
+        seeds = [b"escrow_token", payroll_run.key().as_ref()],
+        bump,
+        token::mint = payroll_run.payment_mint,
+        token::authority = escrow_account,
+        token::token_program = token_program
+    )]
+    pub escrow_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// Receives the tokens left in escrow
+    #[account(
+        mut,
+        token::mint = payroll_run.payment_mint,
+        token::authority = authority,
+        token::token_program = token_program
+    )]
+    pub authority_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
 }
 
 #[account]
@@ -380,6 +749,26 @@ pub struct PayrollRunAccount {
     pub escrow_account: Pubkey,
     pub created_at: i64,
     pub bump: u8,
+    /// Mint of the run's escrow token account, `system_program::ID` for SOL
+    pub payment_mint: Pubkey,
+    /// Token-2022 transfer fees withheld from the run's transfers
+    pub fees_withheld: u64,
+    /// Bump of `escrow_account`, which signs payouts and refunds
+    pub escrow_bump: u8,
+    /// Amount paid out of escrow so far, at most `total_amount`
+    pub total_disbursed: u64,
+}
+
+/// Marks an employee as paid in a payroll run; seeds are
+/// `[b"receipt", payroll_run, employee]`.
+#[account]
+pub struct PaymentReceipt {
+    pub payroll_run: Pubkey,
+    pub employee: Pubkey,
+    pub amount: u64,
+    pub fee: u64,
+    pub paid_at: i64,
+    pub bump: u8,
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
@@ -414,12 +803,20 @@ pub struct EmployeeAdded {
     pub salary: u64,
 }
 
+#[event]
+pub struct EmployeeDeactivated {
+    pub organization: Pubkey,
+    pub employee: Pubkey,
+    pub wallet: Pubkey,
+}
+
 #[event]
 pub struct PayrollScheduled {
     pub organization: Pubkey,
     pub payroll_run: Pubkey,
     pub run_id: u64,
     pub total_amount: u64,
+    pub payment_mint: Pubkey,
 }
 
 #[event]
@@ -427,9 +824,20 @@ pub struct PayrollExecuted {
     pub organization: Pubkey,
     pub payroll_run: Pubkey,
     pub total_disbursed: u64,
+    pub fees_withheld: u64,
     pub employee_count: u16,
 }
 
+#[event]
+pub struct EmployeePaid {
+    pub payroll_run: Pubkey,
+    pub employee: Pubkey,
+    pub wallet: Pubkey,
+    /// Amount taken from escrow; the employee receives `amount - fee`
+    pub amount: u64,
+    pub fee: u64,
+}
+
 #[event]
 pub struct PayrollClosed {
     pub organization: Pubkey,
@@ -455,4 +863,239 @@ pub enum ErrorCode {
     EmployeeNotFound,
     #[msg("Arithmetic overflow")]
     Overflow,
+    #[msg("Token payroll requires the mint, escrow token account, authority token account and token program")]
+    MissingTokenAccounts,
+    #[msg("Payment token does not match the payroll run")]
+    PaymentTokenMismatch,
+    #[msg("Payment destination account not provided")]
+    PaymentAccountMissing,
+    #[msg("Token account is not the employee's associated token account")]
+    InvalidTokenAccount,
+    #[msg("Mint has an extension payroll cannot safely handle")]
+    UnsupportedMintExtension,
+    #[msg("Payment wallet does not match the employee's wallet")]
+    WalletMismatch,
+    #[msg("Payment amount exceeds the employee's salary")]
+    AmountExceedsSalary,
+    #[msg("Payments exceed the payroll run's escrowed total")]
+    RunTotalExceeded,
+    #[msg("Payment receipt account not provided")]
+    ReceiptAccountMissing,
+    #[msg("Employee already paid in this payroll run")]
+    EmployeeAlreadyPaid,
+}
+
+/// Finds an account passed in the remaining accounts by key.
+fn remaining_account<'info>(
+    remaining_accounts: &'info [AccountInfo<'info>],
+    key: &Pubkey,
+) -> Option<&'info AccountInfo<'info>> {
+    remaining_accounts.iter().find(|account| account.key == key)
+}
+
+/// Checks a payment against the `EmployeeAccount` it names: the account must
+/// be this program's employee PDA for the organization, and the employee
+/// active, paid at their wallet in their payment token and at most their
+/// salary.
+fn validate_payment<'info>(
+    organization: &Pubkey,
+    payment: &EmployeePayment,
+    employee_info: &'info AccountInfo<'info>,
+) -> Result<()> {
+    let employee = Account::<EmployeeAccount>::try_from(employee_info)
+        .map_err(|_| error!(ErrorCode::EmployeeNotFound))?;
+    let expected = Pubkey::create_program_address(
+        &[
+            b"employee",
+            organization.as_ref(),
+            employee.wallet.as_ref(),
+            &[employee.bump],
+        ],
+        &crate::ID,
+    )
+    .map_err(|_| error!(ErrorCode::EmployeeNotFound))?;
+    require!(
+        employee.organization == *organization && expected == *employee_info.key,
+        ErrorCode::EmployeeNotFound
+    );
+
+    require!(employee.is_active, ErrorCode::EmployeeNotActive);
+    require_keys_eq!(employee.wallet, payment.wallet, ErrorCode::WalletMismatch);
+    require_keys_eq!(
+        employee.payment_token,
+        payment.payment_token,
+        ErrorCode::PaymentTokenMismatch
+    );
+    require!(
+        payment.amount <= employee.salary,
+        ErrorCode::AmountExceedsSalary
+    );
+    Ok(())
+}
+
+/// Creates the receipt PDA for a payment. Payments are refused for
+/// employees that already have a receipt in the run.
+fn create_receipt<'info>(
+    receipt_info: &'info AccountInfo<'info>,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    receipt_seeds: &[&[u8]],
+    receipt: &PaymentReceipt,
+) -> Result<()> {
+    require_keys_neq!(
+        *receipt_info.owner,
+        crate::ID,
+        ErrorCode::EmployeeAlreadyPaid
+    );
+
+    let space = 8 + 32 + 32 + 8 + 8 + 8 + 1;
+    let rent = Rent::get()?.minimum_balance(space);
+    let balance = receipt_info.lamports();
+    if balance == 0 {
+        system_program::create_account(
+            CpiContext::new_with_signer(
+                system_program.to_account_info(),
+                system_program::CreateAccount {
+                    from: payer.to_account_info(),
+                    to: receipt_info.clone(),
+                },
+                &[receipt_seeds],
+            ),
+            rent,
+            space as u64,
+            &crate::ID,
+        )?;
+    } else {
+        // Lamports sent to the address beforehand would make create_account
+        // fail, so top up, allocate and assign instead
+        if balance < rent {
+            system_program::transfer(
+                CpiContext::new(
+                    system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: payer.to_account_info(),
+                        to: receipt_info.clone(),
+                    },
+                ),
+                rent - balance,
+            )?;
+        }
+        system_program::allocate(
+            CpiContext::new_with_signer(
+                system_program.to_account_info(),
+                system_program::Allocate {
+                    account_to_allocate: receipt_info.clone(),
+                },
+                &[receipt_seeds],
+            ),
+            space as u64,
+        )?;
+        system_program::assign(
+            CpiContext::new_with_signer(
+                system_program.to_account_info(),
+                system_program::Assign {
+                    account_to_assign: receipt_info.clone(),
+                },
+                &[receipt_seeds],
+            ),
+            &crate::ID,
+        )?;
+    }
+
+    let mut data = receipt_info.try_borrow_mut_data()?;
+    receipt.try_serialize(&mut &mut data[..])
+}
+
+/// Transfer fee configuration of a Token-2022 mint, if it charges one.
+fn transfer_fee_config(mint: &InterfaceAccount<Mint>) -> Result<Option<TransferFeeConfig>> {
+    let mint_info = mint.to_account_info();
+    if *mint_info.owner != spl_token_2022::ID {
+        return Ok(None);
+    }
+    let mint_data = mint_info.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint_data)?;
+    Ok(mint_state
+        .get_extension::<TransferFeeConfig>()
+        .ok()
+        .copied())
+}
+
+/// Rejects Token-2022 mints that can move or block escrowed tokens outside of
+/// payroll: non-transferable tokens, a permanent delegate that can spend from
+/// the escrow, and transfer hooks that need accounts payroll does not pass.
+fn check_mint_extensions(mint: &InterfaceAccount<Mint>) -> Result<()> {
+    let mint_info = mint.to_account_info();
+    if *mint_info.owner != spl_token_2022::ID {
+        return Ok(());
+    }
+    let mint_data = mint_info.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint_data)?;
+    let unsupported = mint_state
+        .get_extension_types()?
+        .into_iter()
+        .any(|extension| {
+            matches!(
+                extension,
+                ExtensionType::NonTransferable
+                    | ExtensionType::PermanentDelegate
+                    | ExtensionType::TransferHook
+            )
+        });
+    require!(!unsupported, ErrorCode::UnsupportedMintExtension);
+    Ok(())
+}
+
+/// Moves `amount` tokens with `transfer_checked`, or with
+/// `transfer_checked_with_fee` for transfer-fee mints. Returns the fee
+/// withheld from what `to` receives.
+fn transfer_tokens<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    from: &InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    to: AccountInfo<'info>,
+    authority: AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+    amount: u64,
+) -> Result<u64> {
+    match transfer_fee_config(mint)? {
+        Some(config) => {
+            let fee = config
+                .calculate_epoch_fee(Clock::get()?.epoch, amount)
+                .ok_or(ErrorCode::Overflow)?;
+            token_interface::transfer_checked_with_fee(
+                CpiContext::new_with_signer(
+                    token_program.to_account_info(),
+                    TransferCheckedWithFee {
+                        token_program_id: token_program.to_account_info(),
+                        source: from.to_account_info(),
+                        mint: mint.to_account_info(),
+                        destination: to,
+                        authority,
+                    },
+                    signer_seeds,
+                ),
+                amount,
+                mint.decimals,
+                fee,
+            )?;
+            Ok(fee)
+        }
+        None => {
+            token_interface::transfer_checked(
+                CpiContext::new_with_signer(
+                    token_program.to_account_info(),
+                    TransferChecked {
+                        from: from.to_account_info(),
+                        mint: mint.to_account_info(),
+                        to,
+                        authority,
+                    },
+                    signer_seeds,
+                ),
+                amount,
+                mint.decimals,
+            )?;
+            Ok(0)
+        }
+    }
 }
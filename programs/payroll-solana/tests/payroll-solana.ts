@@ -7,6 +7,9 @@ import {
   SystemProgram,
   LAMPORTS_PER_SOL,
   SYSVAR_RENT_PUBKEY,
+  Transaction,
+  TransactionInstruction,
+  sendAndConfirmTransaction,
 } from "@solana/web3.js";
 import { expect } from "chai";
 import {
@@ -17,6 +20,14 @@ import {
   getAccount,
   getAssociatedTokenAddress,
   ASSOCIATED_TOKEN_PROGRAM_ID,
+  createAssociatedTokenAccount,
+  TOKEN_2022_PROGRAM_ID,
+  ExtensionType,
+  getMintLen,
+  createInitializeMintInstruction,
+  createInitializeTransferFeeConfigInstruction,
+  createInitializeNonTransferableMintInstruction,
+  createInitializePermanentDelegateInstruction,
 } from "@solana/spl-token";
 
 describe("payroll-solana", () => {
@@ -35,6 +46,13 @@ describe("payroll-solana", () => {
   let employee2Pda: PublicKey;
   let usdcMint: PublicKey;
 
+  // Receipt PDA marking an employee as paid in a payroll run
+  const receiptPda = (payrollRun: PublicKey, employee: PublicKey) =>
+    PublicKey.findProgramAddressSync(
+      [Buffer.from("receipt"), payrollRun.toBuffer(), employee.toBuffer()],
+      program.programId
+    )[0];
+
   before(async () => {
     // Generate test keypairs
     organizationAuthority = Keypair.generate();
@@ -362,16 +380,36 @@ describe("payroll-solana", () => {
             isSigner: false,
             isWritable: false,
           },
+          {
+            pubkey: employeeWallet.publicKey,
+            isSigner: false,
+            isWritable: true,
+          },
+          {
+            pubkey: receiptPda(payrollRunPda, employeePda),
+            isSigner: false,
+            isWritable: true,
+          },
         ])
         .signers([organizationAuthority])
         .rpc();
 
       console.log("Payroll execution transaction:", tx);
 
+      // 100 of the 500 escrowed SOL are paid out, so the run stays open
       const payrollRun =
         await program.account.payrollRunAccount.fetch(payrollRunPda);
-      expect(payrollRun.status.completed).to.be.true;
+      expect(payrollRun.status.inProgress).to.be.true;
       expect(payrollRun.totalEmployees).to.equal(1);
+      expect(payrollRun.totalDisbursed.toNumber()).to.equal(
+        100 * LAMPORTS_PER_SOL
+      );
+
+      const receipt = await program.account.paymentReceipt.fetch(
+        receiptPda(payrollRunPda, employeePda)
+      );
+      expect(receipt.employee.toString()).to.equal(employeePda.toString());
+      expect(receipt.amount.toNumber()).to.equal(100 * LAMPORTS_PER_SOL);
 
       const organization =
         await program.account.organizationAccount.fetch(organizationPda);
@@ -388,8 +426,7 @@ describe("payroll-solana", () => {
       );
     });
 
-    it("Fails to execute payroll with invalid status", async () => {
-      // Try to execute an already completed payroll
+    it("Fails to pay the same employee twice in a run", async () => {
       try {
         const employees = [
           {
@@ -418,125 +455,1249 @@ describe("payroll-solana", () => {
               isSigner: false,
               isWritable: false,
             },
+            {
+              pubkey: employeeWallet.publicKey,
+              isSigner: false,
+              isWritable: true,
+            },
+            {
+              pubkey: receiptPda(payrollRunPda, employeePda),
+              isSigner: false,
+              isWritable: true,
+            },
           ])
           .signers([organizationAuthority])
           .rpc();
-        expect.fail("Should have failed with invalid payroll status");
+        expect.fail("Should have failed with employee already paid");
       } catch (error: any) {
-        expect(error.message).to.include("InvalidPayrollStatus");
+        expect(error.message).to.include("EmployeeAlreadyPaid");
       }
     });
 
-    it("Tests USDC token payments", async () => {
-      // Schedule new payroll run for USDC
-      const usdcRunId = new anchor.BN(2);
-      const [usdcPayrollRunPda] = PublicKey.findProgramAddressSync(
-        [
-          Buffer.from("payroll_run"),
-          organizationPda.toBuffer(),
-          usdcRunId.toArrayLike(Buffer, "le", 8),
-        ],
-        program.programId
-      );
+    describe("Employee validation", () => {
+      const runId = new anchor.BN(7);
+      const lowSalaryWallet = Keypair.generate();
+      let lowSalaryPda: PublicKey;
+      let validationRunPda: PublicKey;
+      let validationEscrowPda: PublicKey;
+
+      const executeSolPayments = (
+        payments: {
+          employee: PublicKey;
+          wallet: PublicKey;
+          amount: anchor.BN;
+        }[]
+      ) =>
+        program.methods
+          .executePayroll(
+            payments.map((payment) => ({
+              ...payment,
+              paymentToken: PublicKey.default,
+              tokenAccount: null,
+            }))
+          )
+          .accounts({
+            organization: organizationPda,
+            payrollRun: validationRunPda,
+            escrowAccount: validationEscrowPda,
+            authority: organizationAuthority.publicKey,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .remainingAccounts(
+            payments.flatMap((payment) => [
+              { pubkey: payment.employee, isSigner: false, isWritable: false },
+              { pubkey: payment.wallet, isSigner: false, isWritable: true },
+              {
+                pubkey: receiptPda(validationRunPda, payment.employee),
+                isSigner: false,
+                isWritable: true,
+              },
+            ])
+          )
+          .signers([organizationAuthority])
+          .rpc();
 
-      const [usdcEscrowPda] = PublicKey.findProgramAddressSync(
-        [Buffer.from("escrow"), usdcPayrollRunPda.toBuffer()],
-        program.programId
-      );
+      before(async () => {
+        [lowSalaryPda] = PublicKey.findProgramAddressSync(
+          [
+            Buffer.from("employee"),
+            organizationPda.toBuffer(),
+            lowSalaryWallet.publicKey.toBuffer(),
+          ],
+          program.programId
+        );
+        await program.methods
+          .addEmployee(new anchor.BN(LAMPORTS_PER_SOL), PublicKey.default)
+          .accounts({
+            organization: organizationPda,
+            employee: lowSalaryPda,
+            employeeWallet: lowSalaryWallet.publicKey,
+            authority: organizationAuthority.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([organizationAuthority])
+          .rpc();
 
-      // Create USDC escrow token account
-      const usdcEscrowTokenAccount = await createAccount(
-        provider.connection,
-        organizationAuthority,
-        usdcMint,
-        usdcEscrowPda
-      );
+        [validationRunPda] = PublicKey.findProgramAddressSync(
+          [
+            Buffer.from("payroll_run"),
+            organizationPda.toBuffer(),
+            runId.toArrayLike(Buffer, "le", 8),
+          ],
+          program.programId
+        );
+        [validationEscrowPda] = PublicKey.findProgramAddressSync(
+          [Buffer.from("escrow"), validationRunPda.toBuffer()],
+          program.programId
+        );
 
-      // Mint USDC to escrow
-      await mintTo(
-        provider.connection,
-        organizationAuthority,
-        usdcMint,
-        usdcEscrowTokenAccount,
-        organizationAuthority,
-        1000 * 10 ** 6 // 1000 USDC
-      );
+        await program.methods
+          .schedulePayroll(runId, new anchor.BN(10 * LAMPORTS_PER_SOL))
+          .accounts({
+            organization: organizationPda,
+            payrollRun: validationRunPda,
+            escrowAccount: validationEscrowPda,
+            authority: organizationAuthority.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([organizationAuthority])
+          .rpc();
+      });
 
-      // Create employee USDC token account
-      const employee2UsdcAccount = await createAccount(
-        provider.connection,
-        employee2Wallet,
-        usdcMint,
-        employee2Wallet.publicKey
-      );
+      it("Fails to pay an employee at a different wallet", async () => {
+        try {
+          await executeSolPayments([
+            {
+              employee: employeePda,
+              wallet: Keypair.generate().publicKey,
+              amount: new anchor.BN(LAMPORTS_PER_SOL),
+            },
+          ]);
+          expect.fail("Should have failed with wallet mismatch");
+        } catch (error: any) {
+          expect(error.message).to.include("WalletMismatch");
+        }
+      });
+
+      it("Fails to pay more than the employee's salary", async () => {
+        try {
+          await executeSolPayments([
+            {
+              employee: lowSalaryPda,
+              wallet: lowSalaryWallet.publicKey,
+              amount: new anchor.BN(2 * LAMPORTS_PER_SOL),
+            },
+          ]);
+          expect.fail("Should have failed with amount exceeding salary");
+        } catch (error: any) {
+          expect(error.message).to.include("AmountExceedsSalary");
+        }
+      });
+
+      it("Fails to pay an employee paid in another token", async () => {
+        try {
+          await executeSolPayments([
+            {
+              employee: employee2Pda,
+              wallet: employee2Wallet.publicKey,
+              amount: new anchor.BN(LAMPORTS_PER_SOL),
+            },
+          ]);
+          expect.fail("Should have failed with payment token mismatch");
+        } catch (error: any) {
+          expect(error.message).to.include("PaymentTokenMismatch");
+        }
+      });
+
+      it("Fails when the employee account is missing or not an employee PDA", async () => {
+        const stranger = Keypair.generate().publicKey;
+        try {
+          await executeSolPayments([
+            {
+              employee: organizationPda,
+              wallet: stranger,
+              amount: new anchor.BN(LAMPORTS_PER_SOL),
+            },
+          ]);
+          expect.fail("Should have failed with employee not found");
+        } catch (error: any) {
+          expect(error.message).to.include("EmployeeNotFound");
+        }
+
+        try {
+          await program.methods
+            .executePayroll([
+              {
+                employee: employeePda,
+                wallet: employeeWallet.publicKey,
+                amount: new anchor.BN(LAMPORTS_PER_SOL),
+                paymentToken: PublicKey.default,
+                tokenAccount: null,
+              },
+            ])
+            .accounts({
+              organization: organizationPda,
+              payrollRun: validationRunPda,
+              escrowAccount: validationEscrowPda,
+              authority: organizationAuthority.publicKey,
+              tokenProgram: TOKEN_PROGRAM_ID,
+              systemProgram: SystemProgram.programId,
+            })
+            .remainingAccounts([
+              {
+                pubkey: employeeWallet.publicKey,
+                isSigner: false,
+                isWritable: true,
+              },
+            ])
+            .signers([organizationAuthority])
+            .rpc();
+          expect.fail("Should have failed with employee not found");
+        } catch (error: any) {
+          expect(error.message).to.include("EmployeeNotFound");
+        }
+      });
+
+      it("Fails to pay out more than the run escrowed", async () => {
+        try {
+          await executeSolPayments([
+            {
+              employee: employeePda,
+              wallet: employeeWallet.publicKey,
+              amount: new anchor.BN(11 * LAMPORTS_PER_SOL),
+            },
+          ]);
+          expect.fail("Should have failed with run total exceeded");
+        } catch (error: any) {
+          expect(error.message).to.include("RunTotalExceeded");
+        }
+      });
+
+      it("Fails to pay an employee twice in one batch", async () => {
+        const payment = {
+          employee: lowSalaryPda,
+          wallet: lowSalaryWallet.publicKey,
+          amount: new anchor.BN(LAMPORTS_PER_SOL),
+        };
+        try {
+          await executeSolPayments([payment, payment]);
+          expect.fail("Should have failed with employee already paid");
+        } catch (error: any) {
+          expect(error.message).to.include("EmployeeAlreadyPaid");
+        }
+      });
+
+      it("Fails to pay an inactive employee", async () => {
+        const inactiveWallet = Keypair.generate();
+        const [inactivePda] = PublicKey.findProgramAddressSync(
+          [
+            Buffer.from("employee"),
+            organizationPda.toBuffer(),
+            inactiveWallet.publicKey.toBuffer(),
+          ],
+          program.programId
+        );
+        await program.methods
+          .addEmployee(new anchor.BN(LAMPORTS_PER_SOL), PublicKey.default)
+          .accounts({
+            organization: organizationPda,
+            employee: inactivePda,
+            employeeWallet: inactiveWallet.publicKey,
+            authority: organizationAuthority.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([organizationAuthority])
+          .rpc();
+
+        try {
+          await program.methods
+            .deactivateEmployee()
+            .accounts({
+              organization: organizationPda,
+              employee: inactivePda,
+              authority: employeeWallet.publicKey,
+            })
+            .signers([employeeWallet])
+            .rpc();
+          expect.fail("Should have failed with invalid authority");
+        } catch (error: any) {
+          expect(error.message).to.include("InvalidAuthority");
+        }
 
-      const initialBalance = await getAccount(
-        provider.connection,
-        employee2UsdcAccount
+        await program.methods
+          .deactivateEmployee()
+          .accounts({
+            organization: organizationPda,
+            employee: inactivePda,
+            authority: organizationAuthority.publicKey,
+          })
+          .signers([organizationAuthority])
+          .rpc();
+        const employee = await program.account.employeeAccount.fetch(
+          inactivePda
+        );
+        expect(employee.isActive).to.be.false;
+
+        try {
+          await executeSolPayments([
+            {
+              employee: inactivePda,
+              wallet: inactiveWallet.publicKey,
+              amount: new anchor.BN(LAMPORTS_PER_SOL),
+            },
+          ]);
+          expect.fail("Should have failed with employee not active");
+        } catch (error: any) {
+          expect(error.message).to.include("EmployeeNotActive");
+        }
+      });
+
+      it("Leaves the run pending after rejected payments", async () => {
+        const payrollRun =
+          await program.account.payrollRunAccount.fetch(validationRunPda);
+        expect(payrollRun.status.pending).to.be.true;
+        const rentReserve =
+          await provider.connection.getMinimumBalanceForRentExemption(0);
+        expect(
+          await provider.connection.getBalance(validationEscrowPda)
+        ).to.equal(10 * LAMPORTS_PER_SOL + rentReserve);
+      });
+    });
+
+    describe("Multi-employee SOL settlement", () => {
+      const runId = new anchor.BN(6);
+      const totalAmount = new anchor.BN(15 * LAMPORTS_PER_SOL);
+      const amounts = [3, 5, 7].map(
+        (sol) => new anchor.BN(sol * LAMPORTS_PER_SOL)
       );
+      const wallets = amounts.map(() => Keypair.generate());
+      let employeePdas: PublicKey[];
+      let multiPayrollRunPda: PublicKey;
+      let multiEscrowPda: PublicKey;
+      let disbursedBefore: anchor.BN;
+
+      const executeBatch = (indices: number[]) =>
+        program.methods
+          .executePayroll(
+            indices.map((i) => ({
+              employee: employeePdas[i],
+              wallet: wallets[i].publicKey,
+              amount: amounts[i],
+              paymentToken: PublicKey.default,
+              tokenAccount: null,
+            }))
+          )
+          .accounts({
+            organization: organizationPda,
+            payrollRun: multiPayrollRunPda,
+            escrowAccount: multiEscrowPda,
+            authority: organizationAuthority.publicKey,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .remainingAccounts(
+            indices.flatMap((i) => [
+              { pubkey: employeePdas[i], isSigner: false, isWritable: false },
+              {
+                pubkey: wallets[i].publicKey,
+                isSigner: false,
+                isWritable: true,
+              },
+              {
+                pubkey: receiptPda(multiPayrollRunPda, employeePdas[i]),
+                isSigner: false,
+                isWritable: true,
+              },
+            ])
+          )
+          .signers([organizationAuthority])
+          .rpc();
 
-      // Schedule USDC payroll
-      await program.methods
-        .schedulePayroll(usdcRunId, new anchor.BN(500 * 10 ** 6)) // 500 USDC
-        .accounts({
-          organization: organizationPda,
-          payrollRun: usdcPayrollRunPda,
-          escrowAccount: usdcEscrowPda,
-          authority: organizationAuthority.publicKey,
-          systemProgram: SystemProgram.programId,
-        })
-        .signers([organizationAuthority])
-        .rpc();
+      before(async () => {
+        employeePdas = wallets.map(
+          (wallet) =>
+            PublicKey.findProgramAddressSync(
+              [
+                Buffer.from("employee"),
+                organizationPda.toBuffer(),
+                wallet.publicKey.toBuffer(),
+              ],
+              program.programId
+            )[0]
+        );
 
-      // Execute USDC payroll
-      const usdcEmployees = [
-        {
-          employee: employee2Pda,
-          wallet: employee2Wallet.publicKey,
-          amount: new anchor.BN(100 * 10 ** 6), // 100 USDC
-          paymentToken: usdcMint,
-          tokenAccount: employee2UsdcAccount,
-        },
-      ];
+        for (let i = 0; i < wallets.length; i++) {
+          await program.methods
+            .addEmployee(new anchor.BN(10 * LAMPORTS_PER_SOL), PublicKey.default)
+            .accounts({
+              organization: organizationPda,
+              employee: employeePdas[i],
+              employeeWallet: wallets[i].publicKey,
+              authority: organizationAuthority.publicKey,
+              systemProgram: SystemProgram.programId,
+            })
+            .signers([organizationAuthority])
+            .rpc();
+        }
+
+        [multiPayrollRunPda] = PublicKey.findProgramAddressSync(
+          [
+            Buffer.from("payroll_run"),
+            organizationPda.toBuffer(),
+            runId.toArrayLike(Buffer, "le", 8),
+          ],
+          program.programId
+        );
+        [multiEscrowPda] = PublicKey.findProgramAddressSync(
+          [Buffer.from("escrow"), multiPayrollRunPda.toBuffer()],
+          program.programId
+        );
 
-      await program.methods
-        .executePayroll(usdcEmployees)
-        .accounts({
-          organization: organizationPda,
-          payrollRun: usdcPayrollRunPda,
-          escrowAccount: usdcEscrowPda,
-          escrowTokenAccount: usdcEscrowTokenAccount,
-          authority: organizationAuthority.publicKey,
-          tokenProgram: TOKEN_PROGRAM_ID,
-          systemProgram: SystemProgram.programId,
-        })
-        .remainingAccounts([
-          {
-            pubkey: employee2Pda,
-            isSigner: false,
-            isWritable: false,
-          },
-          {
-            pubkey: employee2UsdcAccount,
-            isSigner: false,
-            isWritable: true,
-          },
-        ])
-        .signers([organizationAuthority])
-        .rpc();
+        await program.methods
+          .schedulePayroll(runId, totalAmount)
+          .accounts({
+            organization: organizationPda,
+            payrollRun: multiPayrollRunPda,
+            escrowAccount: multiEscrowPda,
+            authority: organizationAuthority.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([organizationAuthority])
+          .rpc();
+      });
 
-      const usdcPayrollRun =
-        await program.account.payrollRunAccount.fetch(usdcPayrollRunPda);
-      expect(usdcPayrollRun.status.completed).to.be.true;
+      it("Pays a first batch and keeps the run open", async () => {
+        disbursedBefore = (
+          await program.account.organizationAccount.fetch(organizationPda)
+        ).totalDisbursed;
 
-      // Check employee received USDC
-      const finalBalance = await getAccount(
-        provider.connection,
-        employee2UsdcAccount
-      );
-      expect(
-        finalBalance.amount - initialBalance.amount
-      ).to.be.greaterThanOrEqual(100n * 10n ** 6n);
+        await executeBatch([0, 1]);
+
+        const payrollRun =
+          await program.account.payrollRunAccount.fetch(multiPayrollRunPda);
+        expect(payrollRun.status.inProgress).to.be.true;
+        expect(payrollRun.totalEmployees).to.equal(2);
+        expect(payrollRun.totalDisbursed.toNumber()).to.equal(
+          8 * LAMPORTS_PER_SOL
+        );
+      });
+
+      it("Settles the run with the final batch", async () => {
+        await executeBatch([2]);
+
+        for (let i = 0; i < wallets.length; i++) {
+          const balance = await provider.connection.getBalance(
+            wallets[i].publicKey
+          );
+          expect(balance).to.equal(amounts[i].toNumber());
+        }
+
+        // Only the rent reserve funded at schedule time is left
+        const escrowBalance = await provider.connection.getBalance(
+          multiEscrowPda
+        );
+        expect(escrowBalance).to.equal(
+          await provider.connection.getMinimumBalanceForRentExemption(0)
+        );
+
+        const payrollRun =
+          await program.account.payrollRunAccount.fetch(multiPayrollRunPda);
+        expect(payrollRun.status.completed).to.be.true;
+        expect(payrollRun.totalEmployees).to.equal(3);
+        expect(payrollRun.totalDisbursed.toString()).to.equal(
+          totalAmount.toString()
+        );
+
+        const organization =
+          await program.account.organizationAccount.fetch(organizationPda);
+        expect(
+          organization.totalDisbursed.sub(disbursedBefore).toString()
+        ).to.equal(totalAmount.toString());
+      });
+
+      it("Fails to execute a settled run", async () => {
+        try {
+          await executeBatch([0]);
+          expect.fail("Should have failed with invalid payroll status");
+        } catch (error: any) {
+          expect(error.message).to.include("InvalidPayrollStatus");
+        }
+      });
+
+      it("Closes the settled run", async () => {
+        await program.methods
+          .closePayrollRun()
+          .accounts({
+            organization: organizationPda,
+            payrollRun: multiPayrollRunPda,
+            escrowAccount: multiEscrowPda,
+            authority: organizationAuthority.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([organizationAuthority])
+          .rpc();
+
+        const payrollRun =
+          await program.account.payrollRunAccount.fetch(multiPayrollRunPda);
+        expect(payrollRun.status.cancelled).to.be.true;
+        expect(await provider.connection.getBalance(multiEscrowPda)).to.equal(0);
+      });
+    });
+
+    describe("SOL runs below the rent-exempt minimum", () => {
+      const runId = new anchor.BN(8);
+      const totalAmount = new anchor.BN(1_000);
+      const amounts = [400, 600].map((lamports) => new anchor.BN(lamports));
+      const wallets = amounts.map(() => Keypair.generate());
+      let employeePdas: PublicKey[];
+      let smallRunPda: PublicKey;
+      let smallEscrowPda: PublicKey;
+      let rentReserve: number;
+
+      const payEmployee = (i: number) =>
+        program.methods
+          .executePayroll([
+            {
+              employee: employeePdas[i],
+              wallet: wallets[i].publicKey,
+              amount: amounts[i],
+              paymentToken: PublicKey.default,
+              tokenAccount: null,
+            },
+          ])
+          .accounts({
+            organization: organizationPda,
+            payrollRun: smallRunPda,
+            escrowAccount: smallEscrowPda,
+            authority: organizationAuthority.publicKey,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .remainingAccounts([
+            { pubkey: employeePdas[i], isSigner: false, isWritable: false },
+            { pubkey: wallets[i].publicKey, isSigner: false, isWritable: true },
+            {
+              pubkey: receiptPda(smallRunPda, employeePdas[i]),
+              isSigner: false,
+              isWritable: true,
+            },
+          ])
+          .signers([organizationAuthority])
+          .rpc();
+
+      before(async () => {
+        rentReserve =
+          await provider.connection.getMinimumBalanceForRentExemption(0);
+        employeePdas = wallets.map(
+          (wallet) =>
+            PublicKey.findProgramAddressSync(
+              [
+                Buffer.from("employee"),
+                organizationPda.toBuffer(),
+                wallet.publicKey.toBuffer(),
+              ],
+              program.programId
+            )[0]
+        );
+
+        // Payments of a few lamports need recipients that are already
+        // rent-exempt
+        for (let i = 0; i < wallets.length; i++) {
+          await provider.connection.requestAirdrop(
+            wallets[i].publicKey,
+            LAMPORTS_PER_SOL
+          );
+          await program.methods
+            .addEmployee(new anchor.BN(LAMPORTS_PER_SOL), PublicKey.default)
+            .accounts({
+              organization: organizationPda,
+              employee: employeePdas[i],
+              employeeWallet: wallets[i].publicKey,
+              authority: organizationAuthority.publicKey,
+              systemProgram: SystemProgram.programId,
+            })
+            .signers([organizationAuthority])
+            .rpc();
+        }
+        await new Promise((resolve) => setTimeout(resolve, 1000));
+
+        [smallRunPda] = PublicKey.findProgramAddressSync(
+          [
+            Buffer.from("payroll_run"),
+            organizationPda.toBuffer(),
+            runId.toArrayLike(Buffer, "le", 8),
+          ],
+          program.programId
+        );
+        [smallEscrowPda] = PublicKey.findProgramAddressSync(
+          [Buffer.from("escrow"), smallRunPda.toBuffer()],
+          program.programId
+        );
+      });
+
+      it("Schedules a total below the rent-exempt minimum", async () => {
+        await program.methods
+          .schedulePayroll(runId, totalAmount)
+          .accounts({
+            organization: organizationPda,
+            payrollRun: smallRunPda,
+            escrowAccount: smallEscrowPda,
+            authority: organizationAuthority.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([organizationAuthority])
+          .rpc();
+
+        expect(await provider.connection.getBalance(smallEscrowPda)).to.equal(
+          totalAmount.toNumber() + rentReserve
+        );
+      });
+
+      it("Pays out part of the run and then the rest", async () => {
+        await payEmployee(0);
+        expect(await provider.connection.getBalance(smallEscrowPda)).to.equal(
+          amounts[1].toNumber() + rentReserve
+        );
+
+        await payEmployee(1);
+        expect(await provider.connection.getBalance(smallEscrowPda)).to.equal(
+          rentReserve
+        );
+        const payrollRun =
+          await program.account.payrollRunAccount.fetch(smallRunPda);
+        expect(payrollRun.status.completed).to.be.true;
+      });
+
+      it("Returns the rent reserve when the run is closed", async () => {
+        await program.methods
+          .closePayrollRun()
+          .accounts({
+            organization: organizationPda,
+            payrollRun: smallRunPda,
+            escrowAccount: smallEscrowPda,
+            authority: organizationAuthority.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([organizationAuthority])
+          .rpc();
+
+        expect(await provider.connection.getBalance(smallEscrowPda)).to.equal(0);
+      });
+    });
+
+    describe("USDC payroll", () => {
+      const usdcRunId = new anchor.BN(2);
+      let usdcPayrollRunPda: PublicKey;
+      let usdcEscrowPda: PublicKey;
+      let usdcEscrowTokenAccount: PublicKey;
+      let authorityUsdcAccount: PublicKey;
+      let employee2UsdcAccount: PublicKey;
+
+      before(async () => {
+        [usdcPayrollRunPda] = PublicKey.findProgramAddressSync(
+          [
+            Buffer.from("payroll_run"),
+            organizationPda.toBuffer(),
+            usdcRunId.toArrayLike(Buffer, "le", 8),
+          ],
+          program.programId
+        );
+
+        [usdcEscrowPda] = PublicKey.findProgramAddressSync(
+          [Buffer.from("escrow"), usdcPayrollRunPda.toBuffer()],
+          program.programId
+        );
+
+        [usdcEscrowTokenAccount] = PublicKey.findProgramAddressSync(
+          [Buffer.from("escrow_token"), usdcPayrollRunPda.toBuffer()],
+          program.programId
+        );
+
+        // Fund the organization's USDC account
+        authorityUsdcAccount = await createAssociatedTokenAccount(
+          provider.connection,
+          organizationAuthority,
+          usdcMint,
+          organizationAuthority.publicKey
+        );
+        await mintTo(
+          provider.connection,
+          organizationAuthority,
+          usdcMint,
+          authorityUsdcAccount,
+          organizationAuthority,
+          1000 * 10 ** 6 // 1000 USDC
+        );
+
+        employee2UsdcAccount = await createAssociatedTokenAccount(
+          provider.connection,
+          employee2Wallet,
+          usdcMint,
+          employee2Wallet.publicKey
+        );
+      });
+
+      it("Schedules payroll run with USDC into a PDA-owned escrow", async () => {
+        await program.methods
+          .schedulePayroll(usdcRunId, new anchor.BN(500 * 10 ** 6)) // 500 USDC
+          .accounts({
+            organization: organizationPda,
+            payrollRun: usdcPayrollRunPda,
+            escrowAccount: usdcEscrowPda,
+            authority: organizationAuthority.publicKey,
+            systemProgram: SystemProgram.programId,
+            paymentMint: usdcMint,
+            escrowTokenAccount: usdcEscrowTokenAccount,
+            authorityTokenAccount: authorityUsdcAccount,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([organizationAuthority])
+          .rpc();
+
+        const usdcPayrollRun =
+          await program.account.payrollRunAccount.fetch(usdcPayrollRunPda);
+        expect(usdcPayrollRun.paymentMint.toString()).to.equal(
+          usdcMint.toString()
+        );
+
+        const escrow = await getAccount(
+          provider.connection,
+          usdcEscrowTokenAccount
+        );
+        expect(escrow.owner.toString()).to.equal(usdcEscrowPda.toString());
+        expect(escrow.amount).to.equal(500n * 10n ** 6n);
+
+        const authority = await getAccount(
+          provider.connection,
+          authorityUsdcAccount
+        );
+        expect(authority.amount).to.equal(500n * 10n ** 6n);
+      });
+
+      it("Fails to pay SOL out of a USDC run", async () => {
+        try {
+          await program.methods
+            .executePayroll([
+              {
+                employee: employeePda,
+                wallet: employeeWallet.publicKey,
+                amount: new anchor.BN(LAMPORTS_PER_SOL),
+                paymentToken: PublicKey.default,
+                tokenAccount: null,
+              },
+            ])
+            .accounts({
+              organization: organizationPda,
+              payrollRun: usdcPayrollRunPda,
+              escrowAccount: usdcEscrowPda,
+              paymentMint: usdcMint,
+              escrowTokenAccount: usdcEscrowTokenAccount,
+              authority: organizationAuthority.publicKey,
+              tokenProgram: TOKEN_PROGRAM_ID,
+              systemProgram: SystemProgram.programId,
+            })
+            .remainingAccounts([
+              {
+                pubkey: employeeWallet.publicKey,
+                isSigner: false,
+                isWritable: true,
+              },
+            ])
+            .signers([organizationAuthority])
+            .rpc();
+          expect.fail("Should have failed with payment token mismatch");
+        } catch (error: any) {
+          expect(error.message).to.include("PaymentTokenMismatch");
+        }
+      });
+
+      it("Fails to pay USDC to a token account other than the employee's ATA", async () => {
+        const otherAccount = await createAccount(
+          provider.connection,
+          employee2Wallet,
+          usdcMint,
+          employee2Wallet.publicKey,
+          Keypair.generate()
+        );
+
+        try {
+          await program.methods
+            .executePayroll([
+              {
+                employee: employee2Pda,
+                wallet: employee2Wallet.publicKey,
+                amount: new anchor.BN(100 * 10 ** 6),
+                paymentToken: usdcMint,
+                tokenAccount: otherAccount,
+              },
+            ])
+            .accounts({
+              organization: organizationPda,
+              payrollRun: usdcPayrollRunPda,
+              escrowAccount: usdcEscrowPda,
+              paymentMint: usdcMint,
+              escrowTokenAccount: usdcEscrowTokenAccount,
+              authority: organizationAuthority.publicKey,
+              tokenProgram: TOKEN_PROGRAM_ID,
+              systemProgram: SystemProgram.programId,
+            })
+            .remainingAccounts([
+              {
+                pubkey: employee2Pda,
+                isSigner: false,
+                isWritable: false,
+              },
+              {
+                pubkey: otherAccount,
+                isSigner: false,
+                isWritable: true,
+              },
+              {
+                pubkey: receiptPda(usdcPayrollRunPda, employee2Pda),
+                isSigner: false,
+                isWritable: true,
+              },
+            ])
+            .signers([organizationAuthority])
+            .rpc();
+          expect.fail("Should have failed with invalid token account");
+        } catch (error: any) {
+          expect(error.message).to.include("InvalidTokenAccount");
+        }
+      });
+
+      it("Executes payroll with USDC payments", async () => {
+        const expectedAta = await getAssociatedTokenAddress(
+          usdcMint,
+          employee2Wallet.publicKey
+        );
+        expect(employee2UsdcAccount.toString()).to.equal(
+          expectedAta.toString()
+        );
+
+        await program.methods
+          .executePayroll([
+            {
+              employee: employee2Pda,
+              wallet: employee2Wallet.publicKey,
+              amount: new anchor.BN(100 * 10 ** 6), // 100 USDC
+              paymentToken: usdcMint,
+              tokenAccount: employee2UsdcAccount,
+            },
+          ])
+          .accounts({
+            organization: organizationPda,
+            payrollRun: usdcPayrollRunPda,
+            escrowAccount: usdcEscrowPda,
+            paymentMint: usdcMint,
+            escrowTokenAccount: usdcEscrowTokenAccount,
+            authority: organizationAuthority.publicKey,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .remainingAccounts([
+            {
+              pubkey: employee2Pda,
+              isSigner: false,
+              isWritable: false,
+            },
+            {
+              pubkey: employee2UsdcAccount,
+              isSigner: false,
+              isWritable: true,
+            },
+            {
+              pubkey: receiptPda(usdcPayrollRunPda, employee2Pda),
+              isSigner: false,
+              isWritable: true,
+            },
+          ])
+          .signers([organizationAuthority])
+          .rpc();
+
+        const usdcPayrollRun =
+          await program.account.payrollRunAccount.fetch(usdcPayrollRunPda);
+        expect(usdcPayrollRun.status.inProgress).to.be.true;
+
+        const employeeAccount = await getAccount(
+          provider.connection,
+          employee2UsdcAccount
+        );
+        expect(employeeAccount.amount).to.equal(100n * 10n ** 6n);
+
+        const escrow = await getAccount(
+          provider.connection,
+          usdcEscrowTokenAccount
+        );
+        expect(escrow.amount).to.equal(400n * 10n ** 6n);
+      });
+
+      it("Closes the USDC run and returns the escrowed remainder", async () => {
+        await program.methods
+          .closePayrollRun()
+          .accounts({
+            organization: organizationPda,
+            payrollRun: usdcPayrollRunPda,
+            escrowAccount: usdcEscrowPda,
+            authority: organizationAuthority.publicKey,
+            systemProgram: SystemProgram.programId,
+            paymentMint: usdcMint,
+            escrowTokenAccount: usdcEscrowTokenAccount,
+            authorityTokenAccount: authorityUsdcAccount,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([organizationAuthority])
+          .rpc();
+
+        const authority = await getAccount(
+          provider.connection,
+          authorityUsdcAccount
+        );
+        expect(authority.amount).to.equal(900n * 10n ** 6n);
+
+        const escrowInfo = await provider.connection.getAccountInfo(
+          usdcEscrowTokenAccount
+        );
+        expect(escrowInfo).to.be.null;
+      });
+    });
+
+    describe("Token-2022 payroll", () => {
+      const feeRunId = new anchor.BN(3);
+      const transferFeeBasisPoints = 100; // 1%
+      let feeMint: PublicKey;
+      let employee3Wallet: Keypair;
+      let employee3Pda: PublicKey;
+      let employee3TokenAccount: PublicKey;
+      let authorityFeeTokenAccount: PublicKey;
+      let feePayrollRunPda: PublicKey;
+      let feeEscrowPda: PublicKey;
+      let feeEscrowTokenAccount: PublicKey;
+
+      const createToken2022Mint = async (
+        extensions: ExtensionType[],
+        initializeExtensions: (mint: PublicKey) => TransactionInstruction[]
+      ): Promise<PublicKey> => {
+        const mintKeypair = Keypair.generate();
+        const mintLen = getMintLen(extensions);
+        const lamports =
+          await provider.connection.getMinimumBalanceForRentExemption(mintLen);
+
+        await sendAndConfirmTransaction(
+          provider.connection,
+          new Transaction().add(
+            SystemProgram.createAccount({
+              fromPubkey: organizationAuthority.publicKey,
+              newAccountPubkey: mintKeypair.publicKey,
+              space: mintLen,
+              lamports,
+              programId: TOKEN_2022_PROGRAM_ID,
+            }),
+            ...initializeExtensions(mintKeypair.publicKey),
+            createInitializeMintInstruction(
+              mintKeypair.publicKey,
+              6,
+              organizationAuthority.publicKey,
+              null,
+              TOKEN_2022_PROGRAM_ID
+            )
+          ),
+          [organizationAuthority, mintKeypair]
+        );
+        return mintKeypair.publicKey;
+      };
+
+      const fundedAuthorityAccount = async (
+        mint: PublicKey
+      ): Promise<PublicKey> => {
+        const account = await createAssociatedTokenAccount(
+          provider.connection,
+          organizationAuthority,
+          mint,
+          organizationAuthority.publicKey,
+          undefined,
+          TOKEN_2022_PROGRAM_ID
+        );
+        await mintTo(
+          provider.connection,
+          organizationAuthority,
+          mint,
+          account,
+          organizationAuthority,
+          1000 * 10 ** 6,
+          [],
+          undefined,
+          TOKEN_2022_PROGRAM_ID
+        );
+        return account;
+      };
+
+      const payrollRunAccounts = (runId: anchor.BN) => {
+        const [payrollRun] = PublicKey.findProgramAddressSync(
+          [
+            Buffer.from("payroll_run"),
+            organizationPda.toBuffer(),
+            runId.toArrayLike(Buffer, "le", 8),
+          ],
+          program.programId
+        );
+        const [escrow] = PublicKey.findProgramAddressSync(
+          [Buffer.from("escrow"), payrollRun.toBuffer()],
+          program.programId
+        );
+        const [escrowToken] = PublicKey.findProgramAddressSync(
+          [Buffer.from("escrow_token"), payrollRun.toBuffer()],
+          program.programId
+        );
+        return { payrollRun, escrow, escrowToken };
+      };
+
+      before(async () => {
+        feeMint = await createToken2022Mint(
+          [ExtensionType.TransferFeeConfig],
+          (mint) => [
+            createInitializeTransferFeeConfigInstruction(
+              mint,
+              organizationAuthority.publicKey,
+              organizationAuthority.publicKey,
+              transferFeeBasisPoints,
+              BigInt(10_000 * 10 ** 6),
+              TOKEN_2022_PROGRAM_ID
+            ),
+          ]
+        );
+        authorityFeeTokenAccount = await fundedAuthorityAccount(feeMint);
+
+        employee3Wallet = Keypair.generate();
+        [employee3Pda] = PublicKey.findProgramAddressSync(
+          [
+            Buffer.from("employee"),
+            organizationPda.toBuffer(),
+            employee3Wallet.publicKey.toBuffer(),
+          ],
+          program.programId
+        );
+        await program.methods
+          .addEmployee(new anchor.BN(5000 * 10 ** 6), feeMint)
+          .accounts({
+            organization: organizationPda,
+            employee: employee3Pda,
+            employeeWallet: employee3Wallet.publicKey,
+            authority: organizationAuthority.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([organizationAuthority])
+          .rpc();
+
+        employee3TokenAccount = await createAssociatedTokenAccount(
+          provider.connection,
+          organizationAuthority,
+          feeMint,
+          employee3Wallet.publicKey,
+          undefined,
+          TOKEN_2022_PROGRAM_ID
+        );
+
+        ({
+          payrollRun: feePayrollRunPda,
+          escrow: feeEscrowPda,
+          escrowToken: feeEscrowTokenAccount,
+        } = payrollRunAccounts(feeRunId));
+      });
+
+      it("Grosses up the deposit so the escrow holds the full total", async () => {
+        await program.methods
+          .schedulePayroll(feeRunId, new anchor.BN(500 * 10 ** 6))
+          .accounts({
+            organization: organizationPda,
+            payrollRun: feePayrollRunPda,
+            escrowAccount: feeEscrowPda,
+            authority: organizationAuthority.publicKey,
+            systemProgram: SystemProgram.programId,
+            paymentMint: feeMint,
+            escrowTokenAccount: feeEscrowTokenAccount,
+            authorityTokenAccount: authorityFeeTokenAccount,
+            tokenProgram: TOKEN_2022_PROGRAM_ID,
+          })
+          .signers([organizationAuthority])
+          .rpc();
+
+        const escrow = await getAccount(
+          provider.connection,
+          feeEscrowTokenAccount,
+          undefined,
+          TOKEN_2022_PROGRAM_ID
+        );
+        expect(escrow.amount >= 500n * 10n ** 6n).to.be.true;
+
+        const payrollRun =
+          await program.account.payrollRunAccount.fetch(feePayrollRunPda);
+        expect(payrollRun.feesWithheld.toNumber()).to.be.greaterThan(0);
+      });
+
+      it("Pays the net amount and records the transfer fee", async () => {
+        const before =
+          await program.account.payrollRunAccount.fetch(feePayrollRunPda);
+
+        await program.methods
+          .executePayroll([
+            {
+              employee: employee3Pda,
+              wallet: employee3Wallet.publicKey,
+              amount: new anchor.BN(100 * 10 ** 6),
+              paymentToken: feeMint,
+              tokenAccount: employee3TokenAccount,
+            },
+          ])
+          .accounts({
+            organization: organizationPda,
+            payrollRun: feePayrollRunPda,
+            escrowAccount: feeEscrowPda,
+            paymentMint: feeMint,
+            escrowTokenAccount: feeEscrowTokenAccount,
+            authority: organizationAuthority.publicKey,
+            tokenProgram: TOKEN_2022_PROGRAM_ID,
+            systemProgram: SystemProgram.programId,
+          })
+          .remainingAccounts([
+            {
+              pubkey: employee3Pda,
+              isSigner: false,
+              isWritable: false,
+            },
+            {
+              pubkey: employee3TokenAccount,
+              isSigner: false,
+              isWritable: true,
+            },
+            {
+              pubkey: receiptPda(feePayrollRunPda, employee3Pda),
+              isSigner: false,
+              isWritable: true,
+            },
+          ])
+          .signers([organizationAuthority])
+          .rpc();
+
+        const fee = (100 * 10 ** 6 * transferFeeBasisPoints) / 10_000;
+        const employeeAccount = await getAccount(
+          provider.connection,
+          employee3TokenAccount,
+          undefined,
+          TOKEN_2022_PROGRAM_ID
+        );
+        expect(employeeAccount.amount).to.equal(BigInt(100 * 10 ** 6 - fee));
+
+        const after =
+          await program.account.payrollRunAccount.fetch(feePayrollRunPda);
+        expect(
+          after.feesWithheld.sub(before.feesWithheld).toNumber()
+        ).to.equal(fee);
+
+        const receipt = await program.account.paymentReceipt.fetch(
+          receiptPda(feePayrollRunPda, employee3Pda)
+        );
+        expect(receipt.amount.toNumber()).to.equal(100 * 10 ** 6);
+        expect(receipt.fee.toNumber()).to.equal(fee);
+      });
+
+      it("Closes the run after harvesting the escrow's withheld fees", async () => {
+        await program.methods
+          .closePayrollRun()
+          .accounts({
+            organization: organizationPda,
+            payrollRun: feePayrollRunPda,
+            escrowAccount: feeEscrowPda,
+            authority: organizationAuthority.publicKey,
+            systemProgram: SystemProgram.programId,
+            paymentMint: feeMint,
+            escrowTokenAccount: feeEscrowTokenAccount,
+            authorityTokenAccount: authorityFeeTokenAccount,
+            tokenProgram: TOKEN_2022_PROGRAM_ID,
+          })
+          .signers([organizationAuthority])
+          .rpc();
+
+        const escrowInfo = await provider.connection.getAccountInfo(
+          feeEscrowTokenAccount
+        );
+        expect(escrowInfo).to.be.null;
+      });
+
+      it("Rejects non-transferable mints", async () => {
+        const nonTransferableMint = await createToken2022Mint(
+          [ExtensionType.NonTransferable],
+          (mint) => [
+            createInitializeNonTransferableMintInstruction(
+              mint,
+              TOKEN_2022_PROGRAM_ID
+            ),
+          ]
+        );
+        const authorityAccount =
+          await fundedAuthorityAccount(nonTransferableMint);
+        const runId = new anchor.BN(4);
+        const { payrollRun, escrow, escrowToken } = payrollRunAccounts(runId);
+
+        try {
+          await program.methods
+            .schedulePayroll(runId, new anchor.BN(100 * 10 ** 6))
+            .accounts({
+              organization: organizationPda,
+              payrollRun,
+              escrowAccount: escrow,
+              authority: organizationAuthority.publicKey,
+              systemProgram: SystemProgram.programId,
+              paymentMint: nonTransferableMint,
+              escrowTokenAccount: escrowToken,
+              authorityTokenAccount: authorityAccount,
+              tokenProgram: TOKEN_2022_PROGRAM_ID,
+            })
+            .signers([organizationAuthority])
+            .rpc();
+          expect.fail("Should have failed with unsupported mint extension");
+        } catch (error: any) {
+          expect(error.message).to.include("UnsupportedMintExtension");
+        }
+      });
+
+      it("Rejects mints with a permanent delegate", async () => {
+        const delegatedMint = await createToken2022Mint(
+          [ExtensionType.PermanentDelegate],
+          (mint) => [
+            createInitializePermanentDelegateInstruction(
+              mint,
+              Keypair.generate().publicKey,
+              TOKEN_2022_PROGRAM_ID
+            ),
+          ]
+        );
+        const authorityAccount = await fundedAuthorityAccount(delegatedMint);
+        const runId = new anchor.BN(5);
+        const { payrollRun, escrow, escrowToken } = payrollRunAccounts(runId);
+
+        try {
+          await program.methods
+            .schedulePayroll(runId, new anchor.BN(100 * 10 ** 6))
+            .accounts({
+              organization: organizationPda,
+              payrollRun,
+              escrowAccount: escrow,
+              authority: organizationAuthority.publicKey,
+              systemProgram: SystemProgram.programId,
+              paymentMint: delegatedMint,
+              escrowTokenAccount: escrowToken,
+              authorityTokenAccount: authorityAccount,
+              tokenProgram: TOKEN_2022_PROGRAM_ID,
+            })
+            .signers([organizationAuthority])
+            .rpc();
+          expect.fail("Should have failed with unsupported mint extension");
+        } catch (error: any) {
+          expect(error.message).to.include("UnsupportedMintExtension");
+        }
+      });
     });
   });
 
@@ -664,6 +1825,16 @@ describe("payroll-solana", () => {
               isSigner: false,
               isWritable: false,
             },
+            {
+              pubkey: employeeWallet.publicKey,
+              isSigner: false,
+              isWritable: true,
+            },
+            {
+              pubkey: receiptPda(testPayrollRunPda, employeePda),
+              isSigner: false,
+              isWritable: true,
+            },
           ])
           .signers([organizationAuthority])
           .rpc();
@@ -687,10 +1858,13 @@ describe("payroll-solana", () => {
       }
     });
 
-    it("Fails to close payroll with invalid status", async () => {
-      try {
-        const pendingRunId = new anchor.BN(888);
-        const [pendingPayrollRunPda] = PublicKey.findProgramAddressSync(
+    describe("Closing a run that was never executed", () => {
+      const pendingRunId = new anchor.BN(888);
+      let pendingPayrollRunPda: PublicKey;
+      let pendingEscrowPda: PublicKey;
+
+      before(() => {
+        [pendingPayrollRunPda] = PublicKey.findProgramAddressSync(
           [
             Buffer.from("payroll_run"),
             organizationPda.toBuffer(),
@@ -698,15 +1872,15 @@ describe("payroll-solana", () => {
           ],
           program.programId
         );
-
-        const [pendingEscrowPda] = PublicKey.findProgramAddressSync(
+        [pendingEscrowPda] = PublicKey.findProgramAddressSync(
           [Buffer.from("escrow"), pendingPayrollRunPda.toBuffer()],
           program.programId
         );
+      });
 
-        // Schedule but don't execute
-        await program.methods
-          .schedulePayroll(pendingRunId, new anchor.BN(10 * LAMPORTS_PER_SOL))
+      const closePending = () =>
+        program.methods
+          .closePayrollRun()
           .accounts({
             organization: organizationPda,
             payrollRun: pendingPayrollRunPda,
@@ -717,9 +1891,10 @@ describe("payroll-solana", () => {
           .signers([organizationAuthority])
           .rpc();
 
-        // Try to close pending payroll
+      it("Closes a pending payroll run and returns the whole escrow", async () => {
+        // Schedule but don't execute
         await program.methods
-          .closePayrollRun()
+          .schedulePayroll(pendingRunId, new anchor.BN(10 * LAMPORTS_PER_SOL))
           .accounts({
             organization: organizationPda,
             payrollRun: pendingPayrollRunPda,
@@ -730,10 +1905,26 @@ describe("payroll-solana", () => {
           .signers([organizationAuthority])
           .rpc();
 
-        expect.fail("Should have failed with invalid payroll status");
-      } catch (error: any) {
-        expect(error.message).to.include("InvalidPayrollStatus");
-      }
+        let payrollRun =
+          await program.account.payrollRunAccount.fetch(pendingPayrollRunPda);
+        expect(payrollRun.status.pending).to.be.true;
+
+        await closePending();
+
+        payrollRun =
+          await program.account.payrollRunAccount.fetch(pendingPayrollRunPda);
+        expect(payrollRun.status.cancelled).to.be.true;
+        expect(await provider.connection.getBalance(pendingEscrowPda)).to.equal(0);
+      });
+
+      it("Fails to close a run that is already closed", async () => {
+        try {
+          await closePending();
+          expect.fail("Should have failed with invalid payroll status");
+        } catch (error: any) {
+          expect(error.message).to.include("InvalidPayrollStatus");
+        }
+      });
     });
   });
 
@@ -746,12 +1937,5 @@ describe("payroll-solana", () => {
       );
       expect(org.employeeCount.toNumber()).to.be.lessThan(4294967295); // Max u32
     });
-
-    it("Validates employee is active before payment", async () => {
-      // This is tested through the execute_payroll flow
-      // We verify that only active employees can receive payments
-      const employee = await program.account.employeeAccount.fetch(employeePda);
-      expect(employee.isActive).to.be.true;
-    });
   });
 });